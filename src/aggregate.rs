@@ -0,0 +1,490 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Read, Write};
+
+use serde_json::Value;
+
+use crate::choice::Choice;
+use crate::config::{Config, SortBy};
+use crate::debug;
+use crate::json;
+use crate::reader::{self, ErrorLog};
+
+/// Number of values that are not numbers to report one by one, before only counting them.
+const REPORTED: usize = 10;
+
+/// Whether any of `--count`, `--sum`, `--mean`, `--min`, `--max` or `--distinct` was given, so
+/// that a summary is printed at the end of input instead of each record.
+pub fn requested(config: &Config) -> bool {
+    let opt = &config.opt;
+    opt.count || opt.distinct || numeric(config)
+}
+
+/// Whether any requested aggregate needs the chosen items parsed as numbers.
+fn numeric(config: &Config) -> bool {
+    let opt = &config.opt;
+    opt.sum || opt.mean || opt.min || opt.max
+}
+
+/// Read every record of `read` and print the requested aggregates of what each choice selects,
+/// for the whole input or for each group of records with `--group-by`.
+pub fn run<R: Read, W: Write>(
+    read: R,
+    config: &Config,
+    handle: &mut W,
+    log: &mut ErrorLog,
+) -> io::Result<()> {
+    let mut summary = Summary::new(log.source());
+    reader::for_each(read, config, log, |line, l| {
+        debug::print_record(line, l.as_bytes(), config);
+        summary.add_record(line, l, config, handle)
+    })?;
+    summary.finish(config, handle)
+}
+
+/// The groups of records so far, and the values that could not be parsed as numbers.
+struct Summary {
+    groups: BTreeMap<Vec<String>, Group>,
+    /// With `--presorted`, the key and group of the latest records, printed once the key changes
+    current: Option<(Vec<String>, Group)>,
+    source: String,
+    unparsed: usize,
+}
+
+/// The records sharing a key, which is empty without `--group-by`.
+struct Group {
+    records: usize,
+    columns: Vec<Column>,
+}
+
+/// The aggregates of the items selected by one choice.
+struct Column {
+    count: usize,
+    numbers: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+    distinct: HashSet<String>,
+}
+
+impl Summary {
+    fn new(source: &str) -> Self {
+        Summary {
+            groups: BTreeMap::new(),
+            current: None,
+            source: String::from(source),
+            unparsed: 0,
+        }
+    }
+
+    /// Add the items that each choice selects from `record`, found on `line` of the input, to the
+    /// group of its key, printing the previous group if the key changed in presorted input.
+    fn add_record<W: Write>(
+        &mut self,
+        line: usize,
+        record: &str,
+        config: &Config,
+        handle: &mut W,
+    ) -> io::Result<()> {
+        let (record, _) = config.split_terminator(record);
+        let value = if config.opt.json {
+            Some(json::parse_record(record)?)
+        } else {
+            None
+        };
+
+        let key = key(&config.opt.group_by, record, value.as_ref(), config);
+
+        let group = if config.opt.presorted {
+            if let Some((k, g)) = self.current.take_if(|(k, _)| *k != key) {
+                print_group(&k, &g, config, handle)?;
+            }
+            &mut self
+                .current
+                .get_or_insert_with(|| (key, Group::new(config)))
+                .1
+        } else {
+            self.groups.entry(key).or_insert_with(|| Group::new(config))
+        };
+
+        group.records += 1;
+        for (i, choice) in config.opt.choice.iter().enumerate() {
+            for item in items(choice, record, value.as_ref(), config) {
+                if group.columns[i].add(&item, config) {
+                    continue;
+                }
+                self.unparsed += 1;
+                if self.unparsed <= REPORTED {
+                    eprintln!(
+                        "{}:{}: choice {}: `{}` is not a number",
+                        self.source, line, i, item
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Print the groups not printed yet, in the configured order, and report how many values
+    /// were left out.
+    fn finish<W: Write>(mut self, config: &Config, handle: &mut W) -> io::Result<()> {
+        if let Some((key, group)) = &self.current {
+            print_group(key, group, config, handle)?;
+        }
+        // without --group-by, there is a summary even of no records at all
+        if config.opt.group_by.is_empty() && self.groups.is_empty() && self.current.is_none() {
+            self.groups.insert(Vec::new(), Group::new(config));
+        }
+
+        let mut groups: Vec<_> = self.groups.iter().collect();
+        if config.opt.sort_by == SortBy::Count {
+            groups.sort_by_key(|(_, group)| Reverse(group.records));
+        }
+        for (key, group) in groups {
+            print_group(key, group, config, handle)?;
+        }
+
+        let hint = if config.opt.lenient_numbers {
+            ""
+        } else {
+            ", see --lenient-numbers"
+        };
+        match self.unparsed {
+            0 => {}
+            1 => eprintln!("1 value was not a number and was left out{}", hint),
+            n => eprintln!("{} values were not numbers and were left out{}", n, hint),
+        }
+        Ok(())
+    }
+}
+
+/// What each of `choices` selects from `record`, or from its parsed `value` with `--json`, with
+/// the items of each joined by the choice's separator.
+pub fn key(
+    choices: &[Choice],
+    record: &str,
+    value: Option<&Value>,
+    config: &Config,
+) -> Vec<String> {
+    choices
+        .iter()
+        .map(|choice| {
+            let separator = String::from_utf8_lossy(choice.inner_separator(config));
+            items(choice, record, value, config).join(&separator)
+        })
+        .collect()
+}
+
+/// What `choice` selects from `record`, or from its parsed `value` with `--json`.
+fn items<'a>(
+    choice: &Choice,
+    record: &'a str,
+    value: Option<&'a Value>,
+    config: &Config,
+) -> Vec<Cow<'a, str>> {
+    match value {
+        Some(value) => choice.json_items(value),
+        None => choice.items(record, config),
+    }
+}
+
+/// Write the key of a group followed by the aggregates of each choice, separated as the items and
+/// choices of a record are. Without choices, `--count` is the number of records in the group.
+fn print_group<W: Write>(
+    key: &[String],
+    group: &Group,
+    config: &Config,
+    handle: &mut W,
+) -> io::Result<()> {
+    let mut fields = key.to_vec();
+    if config.opt.choice.is_empty() {
+        fields.push(group.records.to_string());
+    }
+    for (choice, column) in config.opt.choice.iter().zip(&group.columns) {
+        let separator = String::from_utf8_lossy(choice.inner_separator(config));
+        fields.push(column.values(config).join(&separator));
+    }
+
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            handle.write_all(&config.outer_separator)?;
+        }
+        handle.write_all(field.as_bytes())?;
+    }
+    handle.write_all(&[config.opt.terminator])
+}
+
+impl Group {
+    fn new(config: &Config) -> Self {
+        Group {
+            records: 0,
+            columns: config.opt.choice.iter().map(|_| Column::new()).collect(),
+        }
+    }
+}
+
+impl Column {
+    fn new() -> Self {
+        Column {
+            count: 0,
+            numbers: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            distinct: HashSet::new(),
+        }
+    }
+
+    /// Add `item` to the aggregates, returning false if they need it to be a number and it is not.
+    fn add(&mut self, item: &str, config: &Config) -> bool {
+        self.count += 1;
+        if config.opt.distinct && !self.distinct.contains(item) {
+            self.distinct.insert(String::from(item));
+        }
+        if !numeric(config) {
+            return true;
+        }
+
+        match parse_number(item, config.opt.lenient_numbers) {
+            Some(n) => {
+                self.numbers += 1;
+                self.sum += n;
+                self.min = self.min.min(n);
+                self.max = self.max.max(n);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The requested aggregates, in the order count, sum, mean, min, max and distinct. Those of no
+    /// numbers at all are NaN, apart from their sum.
+    fn values(&self, config: &Config) -> Vec<String> {
+        let opt = &config.opt;
+        let none = self.numbers == 0;
+        let mut values = Vec::new();
+        if opt.count {
+            values.push(self.count.to_string());
+        }
+        if opt.sum {
+            values.push(self.sum.to_string());
+        }
+        if opt.mean {
+            values.push((self.sum / self.numbers as f64).to_string());
+        }
+        if opt.min {
+            values.push(if none { f64::NAN } else { self.min }.to_string());
+        }
+        if opt.max {
+            values.push(if none { f64::NAN } else { self.max }.to_string());
+        }
+        if opt.distinct {
+            values.push(self.distinct.len().to_string());
+        }
+        values
+    }
+}
+
+/// Parse `text` as a finite number. Leniently, thousands separators (`,` and `_`) are ignored, as
+/// is anything before the first digit or sign, such as a currency symbol, and anything after the
+/// last digit, such as a unit.
+fn parse_number(text: &str, lenient: bool) -> Option<f64> {
+    let n: f64 = if lenient {
+        text.trim_start_matches(|c: char| !c.is_ascii_digit() && !"+-.".contains(c))
+            .trim_end_matches(|c: char| !c.is_ascii_digit() && c != '.')
+            .replace(&[',', '_'][..], "")
+            .parse()
+            .ok()?
+    } else {
+        text.trim().parse().ok()?
+    };
+    Some(n).filter(|n| n.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summarize(args: &[&str], input: &str) -> String {
+        let mut argv = vec!["choose"];
+        argv.extend_from_slice(args);
+        let config = Config::from_iter(argv);
+        let mut log = ErrorLog::new(&config);
+        let mut out = Vec::new();
+        run(input.as_bytes(), &config, &mut out, &mut log).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn parse_strict_numbers() {
+        assert_eq!(Some(12.5), parse_number("12.5", false));
+        assert_eq!(Some(-3.0), parse_number("-3", false));
+        assert_eq!(None, parse_number("1,234", false));
+        assert_eq!(None, parse_number("12ms", false));
+        assert_eq!(None, parse_number("inf", false));
+    }
+
+    #[test]
+    fn parse_lenient_numbers() {
+        assert_eq!(Some(1234.5), parse_number("1,234.5", true));
+        assert_eq!(Some(12.0), parse_number("12ms", true));
+        assert_eq!(Some(1200.0), parse_number("$1_200", true));
+        assert_eq!(Some(45.0), parse_number("45%", true));
+        assert_eq!(Some(-1.5e3), parse_number("-1.5e3 KB", true));
+        assert_eq!(None, parse_number("n/a", true));
+    }
+
+    #[test]
+    fn aggregates_in_fixed_order() {
+        let input = "a 3\nb 1\na 2\n";
+        assert_eq!(
+            "3 6 2 1 3 3\n",
+            summarize(
+                &[
+                    "--max",
+                    "--min",
+                    "--mean",
+                    "--sum",
+                    "--count",
+                    "--distinct",
+                    "1"
+                ],
+                input
+            )
+        );
+    }
+
+    #[test]
+    fn aggregates_per_choice() {
+        let input = "a 3\nb 1\na 2\n";
+        assert_eq!(
+            "3|2|3,3",
+            summarize(&["--distinct", "--count", "0", "1/,", "-o", "|"], input).trim_end()
+        );
+    }
+
+    #[test]
+    fn aggregates_every_item_of_a_range() {
+        assert_eq!("10\n", summarize(&["--sum", "0:1"], "1 2\n3 4\n"));
+    }
+
+    #[test]
+    fn aggregates_leave_out_unparsed_values() {
+        assert_eq!(
+            "3 4 2\n",
+            summarize(&["--count", "--sum", "--mean", "0"], "1\nx\n3\n")
+        );
+    }
+
+    #[test]
+    fn aggregates_of_no_numbers() {
+        assert_eq!(
+            "0 NaN NaN NaN\n",
+            summarize(&["--sum", "--mean", "--min", "--max", "0"], "x\n")
+        );
+    }
+
+    #[test]
+    fn aggregates_after_transforms() {
+        assert_eq!("3\n", summarize(&["--distinct", "0|lower"], "A\na\nB\nC\n"));
+    }
+
+    #[test]
+    fn aggregates_json() {
+        let input = "{\"ms\": 5, \"tags\": [\"a\", \"b\"]}\n{\"ms\": \"7\", \"tags\": [\"a\"]}\n";
+        assert_eq!(
+            "12 2 0 2\n",
+            summarize(&["--json", "--sum", "--distinct", ".ms", "tags.0:"], input)
+        );
+    }
+
+    #[test]
+    fn aggregates_skip_bad_json() {
+        let config = Config::from_iter(vec!["choose", "--json", "--sum", ".ms"]);
+        let mut log = ErrorLog::new(&config);
+        let mut out = Vec::new();
+        let input = "{\"ms\": 5}\nnot json\n{\"ms\": 7}\n";
+        run(input.as_bytes(), &config, &mut out, &mut log).unwrap();
+        assert_eq!("12\n", String::from_utf8(out).unwrap());
+        assert_eq!(5, log.finish().unwrap_err().exit_code());
+    }
+
+    #[test]
+    fn group_key_of_reversed_range_in_print_order() {
+        assert_eq!(
+            "b a 2\nd c 1\n",
+            summarize(&["--group-by", "1:0", "--count"], "a b\na b\nc d\n")
+        );
+    }
+
+    #[test]
+    fn count_records_without_choices() {
+        assert_eq!("3\n", summarize(&["--count"], "a\n\nb\n"));
+        assert_eq!("0\n", summarize(&["--count"], ""));
+    }
+
+    const GROUPS: &str = "a 1\nb 2\na 3\nc 4\nc 5\n";
+
+    #[test]
+    fn groups_in_key_order() {
+        assert_eq!(
+            "a 2 4\nb 1 2\nc 2 9\n",
+            summarize(&["--group-by", "0", "--count", "--sum", "1"], GROUPS)
+        );
+        assert_eq!(
+            "a,2\nb,1\nc,2\n",
+            summarize(&["--group-by", "0", "--count", "-o", ","], GROUPS)
+        );
+    }
+
+    #[test]
+    fn groups_in_count_order() {
+        assert_eq!(
+            "a 2\nc 2\nb 1\n",
+            summarize(
+                &["--group-by", "0", "--count", "--sort-by", "count"],
+                GROUPS
+            )
+        );
+    }
+
+    #[test]
+    fn groups_of_several_keys() {
+        assert_eq!(
+            "a 1:x 2\nb 1:y 1\n",
+            summarize(
+                &["--group-by", "0", "--group-by", "1:2/:", "--count"],
+                "a 1 x\nb 1 y\na 1 x\n"
+            )
+        );
+    }
+
+    #[test]
+    fn groups_after_transforms() {
+        assert_eq!(
+            "a 3\n",
+            summarize(&["--group-by", "0|lower", "--count"], "a\nA\na\n")
+        );
+    }
+
+    #[test]
+    fn no_groups_of_no_records() {
+        assert_eq!("", summarize(&["--group-by", "0", "--count"], ""));
+    }
+
+    #[test]
+    fn presorted_groups_printed_on_key_change() {
+        let config = Config::from_iter(vec!["choose", "--group-by", "0", "--presorted", "--count"]);
+        let mut summary = Summary::new("<stdin>");
+        let mut out = Vec::new();
+        summary.add_record(1, "a 1\n", &config, &mut out).unwrap();
+        summary.add_record(2, "a 2\n", &config, &mut out).unwrap();
+        assert!(out.is_empty());
+        summary.add_record(3, "b 3\n", &config, &mut out).unwrap();
+        assert_eq!(b"a 2\n".to_vec(), out);
+        summary.add_record(4, "a 4\n", &config, &mut out).unwrap();
+        summary.finish(&config, &mut out).unwrap();
+        assert_eq!(b"a 2\nb 1\na 1\n".to_vec(), out);
+    }
+}
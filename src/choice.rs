@@ -1,8 +1,17 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::convert::TryInto;
-use std::io::{BufWriter, Write};
-use std::iter::FromIterator;
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
 
-use crate::config::Config;
+use serde_json::Value;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{Config, Straddle};
+use crate::json;
+use crate::transform::Pipeline;
 use crate::writeable::Writeable;
 use crate::writer::WriteReceiver;
 
@@ -10,8 +19,16 @@ use crate::writer::WriteReceiver;
 pub struct Choice {
     pub start: isize,
     pub end: isize,
+    pub path: Vec<String>,
+    /// SGR sequence to color this choice's output with, if any
+    pub color: Option<&'static str>,
+    /// Separator between the items of this choice, overriding the output field separator
+    pub separator: Option<String>,
+    /// Changes made to each item before it is written
+    pub transforms: Pipeline,
     negative_index: bool,
     reversed: bool,
+    sliced: bool,
 }
 
 impl Choice {
@@ -21,27 +38,391 @@ impl Choice {
         Choice {
             start,
             end,
+            path: Vec::new(),
+            color: None,
+            separator: None,
+            transforms: Pipeline::default(),
             negative_index,
             reversed,
+            sliced: true,
+        }
+    }
+
+    /// Select the whole value found at `path` within a JSON record.
+    pub fn from_path(path: Vec<String>) -> Self {
+        Choice {
+            path,
+            sliced: false,
+            ..Choice::new(0, isize::MAX)
+        }
+    }
+
+    /// Apply this choice as a slice of the JSON array found at `path`.
+    pub fn with_path(mut self, path: Vec<String>) -> Self {
+        self.path = path;
+        self
+    }
+
+    pub fn with_separator(mut self, separator: Option<String>) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    pub fn with_transforms(mut self, transforms: Pipeline) -> Self {
+        self.transforms = transforms;
+        self
+    }
+
+    /// The separator written between the items of this choice.
+    pub fn inner_separator<'a>(&'a self, config: &'a Config) -> &'a [u8] {
+        match &self.separator {
+            Some(s) => s.as_bytes(),
+            None => &config.output_separator,
         }
     }
 
     pub fn print_choice<W: Write>(
         &self,
-        line: &String,
+        line: &str,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        let (line, _) = config.split_terminator(line);
+        if config.opt.bytes {
+            self.print_choice_bytes(line.as_bytes(), config, handle)
+        } else if config.opt.cols {
+            self.print_choice_columns(line, config, handle)
+        } else if config.opt.character_wise && self.inner_separator(config).is_empty() {
+            self.print_choice_char_slice(line, config, handle)
+        } else if config.opt.character_wise {
+            let line_chars = line.chars();
+            self.print_choice_generic(line_chars, config, handle)
+        } else if config.opt.graphemes {
+            let line_graphemes = line.graphemes(true);
+            self.print_choice_generic(line_graphemes, config, handle)
+        } else {
+            let line_iter = config.split_fields(line);
+            self.print_choice_generic(line_iter, config, handle)
+        }
+    }
+
+    pub fn print_choice_json<W: Write>(
+        &self,
+        record: &Value,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        let value = match json::lookup(record, &self.path) {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        match value {
+            Value::Array(items) if self.sliced => {
+                self.print_choice_generic(items.iter(), config, handle)
+            }
+            _ if self.sliced => Ok(()),
+            _ => handle.write_choice(value, self, config, false),
+        }
+    }
+
+    /// Write the selected bytes of `line`, which need not be valid UTF-8, with a single write
+    /// unless they are reversed or there is an output separator to write between them.
+    pub fn print_choice_bytes<W: Write>(
+        &self,
+        line: &[u8],
         config: &Config,
         handle: &mut BufWriter<W>,
-    ) {
+    ) -> io::Result<()> {
+        let (mut lo, mut hi, reversed) = match self.get_bounds(line.len()) {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+
+        // any byte but a continuation byte starts a character, or stands for one if invalid
+        let is_boundary = |i: usize| i == line.len() || (line[i] as i8) >= -0x40;
+        if config.opt.utf8_boundaries {
+            while !is_boundary(lo) {
+                if lo == hi {
+                    return Ok(());
+                }
+                lo += 1;
+            }
+            while !is_boundary(hi + 1) {
+                if hi == lo {
+                    return Ok(());
+                }
+                hi -= 1;
+            }
+        }
+
+        let separator = self.inner_separator(config);
+        if !reversed && separator.is_empty() {
+            return handle.write_choice(&line[lo..=hi], self, config, false);
+        }
+
+        // otherwise each byte, or each character with --utf8-boundaries, is written on its own
+        let starts_unit = |i: usize| !config.opt.utf8_boundaries || is_boundary(i);
+        let mut write_unit = |start: usize, first: bool| {
+            let end = (start + 1..=hi + 1)
+                .find(|&i| starts_unit(i))
+                .unwrap_or(hi + 1);
+            if !first {
+                handle.write_separator(separator)?;
+            }
+            handle.write_choice(&line[start..end], self, config, false)
+        };
+        let starts = (lo..=hi).filter(|&i| starts_unit(i));
+        if reversed {
+            for (n, start) in starts.rev().enumerate() {
+                write_unit(start, n == 0)?;
+            }
+        } else {
+            for (n, start) in starts.enumerate() {
+                write_unit(start, n == 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the selected characters of `line`, as a single slice of it unless reversed. Only
+    /// valid when there is no output separator to write between characters.
+    fn print_choice_char_slice<W: Write>(
+        &self,
+        line: &str,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        let (lo, hi, reversed) = match self.get_bounds(line.chars().count()) {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+
+        let mut offsets = line
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(line.len()));
+        let start = offsets.nth(lo).unwrap_or(line.len());
+        let end = offsets.nth(hi - lo).unwrap_or(line.len());
+
+        if reversed {
+            for c in line[start..end].chars().rev() {
+                handle.write_choice(c, self, config, false)?;
+            }
+        } else {
+            handle.write_choice(&line[start..end], self, config, false)?;
+        }
+        Ok(())
+    }
+
+    /// Write the grapheme clusters of `line` that fall within the selected display columns.
+    fn print_choice_columns<W: Write>(
+        &self,
+        line: &str,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        let (lo, hi, reversed) = match self.get_bounds(line.width()) {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+
+        let mut pieces = Vec::new();
+        let mut col = 0;
+        for g in line.graphemes(true) {
+            let width = g.width();
+            let last = col + width.max(1) - 1;
+            if col > hi {
+                break;
+            } else if col >= lo && last <= hi {
+                pieces.push(Cow::Borrowed(g));
+            } else if last >= lo && config.opt.straddle == Straddle::Pad {
+                // a cluster may be wider than one wide character, as Hangul syllables made of
+                // several jamo are
+                pieces.push(Cow::Owned(" ".repeat(last.min(hi) + 1 - col.max(lo))));
+            }
+            col += width;
+        }
+
+        if reversed {
+            pieces.reverse();
+        }
+        for (i, piece) in pieces.iter().enumerate() {
+            handle.write_choice(piece.as_ref(), self, config, i + 1 < pieces.len())?;
+        }
+        Ok(())
+    }
+
+    /// Resolve this choice against a sequence of `len` items, returning the inclusive bounds of
+    /// the selection and whether it is to be written in reverse. Only a range starting from a
+    /// negative index or ending at a positive one may be reversed; `5:-3` on a short line selects
+    /// nothing. Without a negative index, whether the range is reversed was settled before `-x`
+    /// moved its end, so `-x 2:2` selects nothing rather than reversing.
+    pub fn get_bounds(&self, len: usize) -> Option<(usize, usize, bool)> {
+        let len: isize = len.try_into().ok()?;
+        // an end moved below 0 by `-x` is before the first item, not counted from the last
+        let resolve = |i: isize| {
+            if i < 0 && self.negative_index {
+                len + i
+            } else {
+                i
+            }
+        };
+        let (start, end) = (resolve(self.start), resolve(self.end));
+        let reversed = if self.negative_index {
+            end < start
+        } else {
+            self.reversed
+        };
+        if reversed && self.start >= 0 && self.end < 0 {
+            return None;
+        }
+        let (lo, hi) = if reversed { (end, start) } else { (start, end) };
+        let (lo, hi) = (lo.max(0), hi.min(len - 1));
+
+        if lo > hi {
+            return None;
+        }
+        Some((lo.try_into().ok()?, hi.try_into().ok()?, reversed))
+    }
+
+    /// The byte spans within `line`, which has no terminator, of the fields, characters or
+    /// grapheme clusters this choice selects, in the order they appear in the line.
+    pub fn spans(&self, line: &str, config: &Config) -> Vec<Range<usize>> {
+        let units = Choice::units(line, config);
+        match self.get_bounds(units.len()) {
+            Some((lo, hi, _)) => units[lo..=hi].to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The byte spans of all the fields, characters or grapheme clusters of `line`.
+    fn units(line: &str, config: &Config) -> Vec<Range<usize>> {
         if config.opt.character_wise {
-            let line_chars = line[0..line.len() - 1].chars();
-            self.print_choice_generic(line_chars, config, handle);
+            line.char_indices()
+                .map(|(i, c)| i..i + c.len_utf8())
+                .collect()
+        } else if config.opt.graphemes {
+            line.grapheme_indices(true)
+                .map(|(i, g)| i..i + g.len())
+                .collect()
+        } else {
+            config
+                .split_fields(line)
+                .map(|field| {
+                    let start = field.as_ptr() as usize - line.as_ptr() as usize;
+                    start..start + field.len()
+                })
+                .collect()
+        }
+    }
+
+    /// The text of each item this choice selects from `line`, which has no terminator, after its
+    /// transforms, in the order they are printed. Empty items are left out, as they are when
+    /// printing.
+    pub fn items<'a>(&self, line: &'a str, config: &Config) -> Vec<Cow<'a, str>> {
+        self.indexed_items(line, config)
+            .into_iter()
+            .map(|(_, _, item)| item)
+            .collect()
+    }
+
+    /// Like `items`, along with the index of each item in `line` and its position in the selection,
+    /// counted from the end that is printed first, so that empty items still take up a position.
+    pub fn indexed_items<'a>(
+        &self,
+        line: &'a str,
+        config: &Config,
+    ) -> Vec<(usize, usize, Cow<'a, str>)> {
+        let units = Choice::units(line, config);
+        let (lo, hi, reversed) = match self.get_bounds(units.len()) {
+            Some(b) => b,
+            None => return Vec::new(),
+        };
+        let mut items: Vec<_> = (lo..=hi)
+            .map(|i| (i, &line[units[i].clone()]))
+            .filter(|(_, item)| !item.is_empty())
+            .map(|(i, item)| {
+                let position = if reversed { hi - i } else { i - lo };
+                (i, position, self.transforms.apply(Cow::Borrowed(item)))
+            })
+            .collect();
+        if reversed {
+            items.reverse();
+        }
+        items
+    }
+
+    /// The text of each item this choice selects from a JSON record, after its transforms, in the
+    /// order they are printed.
+    pub fn json_items<'a>(&self, record: &'a Value) -> Vec<Cow<'a, str>> {
+        let (values, reversed) = match json::lookup(record, &self.path) {
+            Some(Value::Array(items)) if self.sliced => match self.get_bounds(items.len()) {
+                Some((lo, hi, reversed)) => (&items[lo..=hi], reversed),
+                None => (&[][..], false),
+            },
+            Some(_) if self.sliced => (&[][..], false),
+            Some(value) => (std::slice::from_ref(value), false),
+            None => (&[][..], false),
+        };
+        let mut items: Vec<_> = values
+            .iter()
+            .filter(|value| !Writeable::is_empty(value))
+            .map(|value| {
+                let text = match value {
+                    Value::String(s) => Cow::Borrowed(s.as_str()),
+                    v => Cow::Owned(v.to_string()),
+                };
+                self.transforms.apply(text)
+            })
+            .collect();
+        if reversed {
+            items.reverse();
+        }
+        items
+    }
+
+    /// Format this choice as the argument that would select it with `-x`, such as `0:3` for a
+    /// choice of `0:2` once `-x` has been applied.
+    pub fn exclusive(&self) -> Exclusive<'_> {
+        Exclusive(self)
+    }
+
+    /// Format this choice as its argument, with `start` and `end` in place of its own, writing a
+    /// range that starts and ends at the same place as a single index if `index` is set.
+    fn fmt_spec(
+        &self,
+        f: &mut fmt::Formatter,
+        start: isize,
+        end: isize,
+        index: bool,
+    ) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}", self.path.join("."))?;
+            if !self.sliced {
+                return Ok(());
+            }
+            write!(f, ".")?;
+        }
+        if index && start == end {
+            write!(f, "{}", start)?;
         } else {
-            let line_iter = config
-                .separator
-                .split(line)
-                .filter(|s| !s.is_empty() || config.opt.non_greedy);
-            self.print_choice_generic(line_iter, config, handle);
+            write!(f, "{}:", start)?;
+            if end != isize::MAX {
+                write!(f, "{}", end)?;
+            }
+        }
+        if let Some(separator) = &self.separator {
+            write!(f, "/{}", separator)?;
         }
+        if !self.transforms.is_empty() {
+            write!(f, "|{}", self.transforms)?;
+        }
+        Ok(())
+    }
+
+    pub fn has_path(&self) -> bool {
+        !self.path.is_empty() || !self.sliced
     }
 
     pub fn is_reverse_range(&self) -> bool {
@@ -52,40 +433,38 @@ impl Choice {
         self.negative_index
     }
 
-    fn print_choice_generic<W, T, I>(&self, mut iter: I, config: &Config, handle: &mut BufWriter<W>)
+    pub fn print_choice_generic<W, T, I>(
+        &self,
+        mut iter: I,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()>
     where
         W: Write,
         T: Writeable + Copy,
         I: Iterator<Item = T>,
     {
         if self.is_reverse_range() && !self.has_negative_index() {
-            self.print_choice_reverse(iter, config, handle);
+            self.print_choice_reverse(iter, config, handle)
         } else if self.has_negative_index() {
-            self.print_choice_negative(iter, config, handle);
+            self.print_choice_negative(iter, config, handle)
         } else {
             if self.start > 0 {
                 iter.nth((self.start - 1).try_into().unwrap());
             }
             let range = self.end.checked_sub(self.start).unwrap();
-            Choice::print_choice_loop_max_items(iter, config, handle, range);
+            self.print_choice_loop_max_items(iter, config, handle, range)
         }
     }
 
-    fn print_choice_loop<W, T, I>(iter: I, config: &Config, handle: &mut BufWriter<W>)
-    where
-        W: Write,
-        T: Writeable + Copy,
-        I: Iterator<Item = T>,
-    {
-        Choice::print_choice_loop_max_items(iter, config, handle, isize::max_value());
-    }
-
     fn print_choice_loop_max_items<W, T, I>(
+        &self,
         iter: I,
         config: &Config,
         handle: &mut BufWriter<W>,
         max_items: isize,
-    ) where
+    ) -> io::Result<()>
+    where
         W: Write,
         T: Writeable + Copy,
         I: Iterator<Item = T>,
@@ -94,39 +473,87 @@ impl Choice {
         for i in 0..=max_items {
             match peek_iter.next() {
                 Some(s) => {
-                    handle.write_choice(s, config, peek_iter.peek().is_some() && i != max_items);
+                    handle.write_choice(
+                        s,
+                        self,
+                        config,
+                        peek_iter.peek().is_some() && i != max_items,
+                    )?;
                 }
                 None => break,
             };
         }
+        Ok(())
     }
 
-    fn print_choice_negative<W, T, I>(&self, iter: I, config: &Config, handle: &mut BufWriter<W>)
+    /// Print a choice with a negative index in a single pass over `iter`, holding only the last
+    /// items that a negative index could refer to, plus any items already known to be part of a
+    /// reversed selection.
+    fn print_choice_negative<W, T, I>(
+        &self,
+        iter: I,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()>
     where
         W: Write,
         T: Writeable + Copy,
         I: Iterator<Item = T>,
     {
-        let vec = Vec::from_iter(iter);
-        let (start, end) = self.get_negative_start_end(&vec);
-
-        if end > start {
-            for word in vec[start..std::cmp::min(end, vec.len() - 1)].iter() {
-                handle.write_choice(*word, config, true);
-            }
-            handle.write_choice(vec[std::cmp::min(end, vec.len() - 1)], config, false);
-        } else if self.start < 0 {
-            for word in vec[end + 1..=std::cmp::min(start, vec.len() - 1)]
-                .iter()
-                .rev()
-            {
-                handle.write_choice(*word, config, true);
+        let window = [self.start, self.end]
+            .iter()
+            .filter(|i| **i < 0)
+            .map(|i| i.unsigned_abs())
+            .max()
+            .unwrap_or(0);
+        let mut ring = VecDeque::new();
+        let mut held = Vec::new();
+        let mut held_start = 0;
+        let mut len = 0;
+
+        for item in iter {
+            if ring.len() == window {
+                let evicted = ring.pop_front().unwrap();
+                let i = (len - window) as isize;
+                if self.start >= 0 && i >= self.start {
+                    // a later item is certain to be selected, as the end of the range is within
+                    // the window
+                    handle.write_choice(evicted, self, config, true)?;
+                } else if self.start < 0 && self.end >= 0 && i >= self.end {
+                    if held.is_empty() {
+                        held_start = len - window;
+                    }
+                    held.push(evicted);
+                }
             }
-            handle.write_choice(vec[end], config, false);
+            ring.push_back(item);
+            len += 1;
+        }
+
+        let (lo, hi, reversed) = match self.get_bounds(len) {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+        let ring_start = len - ring.len();
+        let selected = (held_start..held_start + held.len())
+            .zip(held)
+            .chain((ring_start..len).zip(ring))
+            .filter(|(i, _)| lo <= *i && *i <= hi)
+            .map(|(_, item)| item);
+
+        if reversed {
+            self.print_choice_loop_max_items(selected.rev(), config, handle, isize::MAX)
+        } else {
+            self.print_choice_loop_max_items(selected, config, handle, isize::MAX)
         }
     }
 
-    fn print_choice_reverse<W, T, I>(&self, mut iter: I, config: &Config, handle: &mut BufWriter<W>)
+    fn print_choice_reverse<W, T, I>(
+        &self,
+        mut iter: I,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()>
     where
         W: Write,
         T: Writeable + Copy,
@@ -149,32 +576,33 @@ impl Choice {
         }
 
         let mut peek_iter = stack.iter().rev().peekable();
-        loop {
-            match peek_iter.next() {
-                Some(s) => handle.write_choice(*s, config, peek_iter.peek().is_some()),
-                None => break,
-            }
+        while let Some(s) = peek_iter.next() {
+            handle.write_choice(*s, self, config, peek_iter.peek().is_some())?;
         }
+        Ok(())
     }
+}
 
-    fn get_negative_start_end<T>(&self, vec: &Vec<T>) -> (usize, usize) {
-        let start = if self.start >= 0 {
-            self.start.try_into().unwrap()
-        } else {
-            vec.len()
-                .checked_sub(self.start.abs().try_into().unwrap())
-                .unwrap()
-        };
+/// Formats the choice as the argument that would select it, such as `3`, `1:4` or `tags.0:2`.
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_spec(f, self.start, self.end, true)
+    }
+}
 
-        let end = if self.end >= 0 {
-            self.end.try_into().unwrap()
-        } else {
-            vec.len()
-                .checked_sub(self.end.abs().try_into().unwrap())
-                .unwrap()
-        };
+/// A choice formatted as the argument that would select it with `-x`.
+pub struct Exclusive<'a>(&'a Choice);
 
-        return (start, end);
+impl fmt::Display for Exclusive<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // put back the end that `-x` moved, so that a range it left empty, such as `:0`, is
+        // written as one rather than as a range to a negative index
+        let Exclusive(choice) = self;
+        if choice.is_reverse_range() {
+            choice.fmt_spec(f, choice.start.saturating_add(1), choice.end, false)
+        } else {
+            choice.fmt_spec(f, choice.start, choice.end.saturating_add(1), false)
+        }
     }
 }
 
@@ -193,39 +621,36 @@ mod tests {
             I: IntoIterator,
             I::Item: Into<OsString> + Clone,
         {
-            return Config::new(Opt::from_iter(iter));
+            // output in tests is never to a terminal, even when the tests run in one
+            Config::new(Opt::from_iter(iter), false).unwrap()
         }
     }
 
     struct MockStdout {
-        pub buffer: String,
+        pub buffer: Vec<u8>,
     }
 
     impl MockStdout {
         fn new() -> Self {
-            MockStdout {
-                buffer: String::new(),
-            }
+            MockStdout { buffer: Vec::new() }
         }
 
         fn str_from_buf_writer(b: BufWriter<MockStdout>) -> String {
-            match b.into_inner() {
+            let buffer = match b.into_inner() {
                 Ok(b) => b.buffer,
                 Err(_) => panic!("Failed to access BufWriter inner writer"),
-            }
-            .trim_end()
-            .to_string()
+            };
+            String::from_utf8(buffer)
+                .expect("Output was not valid UTF-8")
+                .trim_end()
+                .to_string()
         }
     }
 
     impl Write for MockStdout {
         fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            let mut bytes_written = 0;
-            for i in buf {
-                self.buffer.push(*i as char);
-                bytes_written += 1;
-            }
-            Ok(bytes_written)
+            self.buffer.extend_from_slice(buf);
+            Ok(buf.len())
         }
 
         fn flush(&mut self) -> io::Result<()> {
@@ -241,11 +666,9 @@ mod tests {
             let config = Config::from_iter(vec!["choose", "0"]);
             let mut handle = BufWriter::new(MockStdout::new());
 
-            config.opt.choice[0].print_choice(
-                &String::from("rust is pretty cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(&String::from("rust is pretty cool"), &config, &mut handle)
+                .unwrap();
 
             assert_eq!(
                 String::from("rust"),
@@ -258,11 +681,9 @@ mod tests {
             let config = Config::from_iter(vec!["choose", "10"]);
             let mut handle = BufWriter::new(MockStdout::new());
 
-            config.opt.choice[0].print_choice(
-                &String::from("rust is pretty cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(&String::from("rust is pretty cool"), &config, &mut handle)
+                .unwrap();
 
             assert_eq!(String::new(), MockStdout::str_from_buf_writer(handle));
         }
@@ -273,22 +694,18 @@ mod tests {
             let mut handle = BufWriter::new(MockStdout::new());
             let mut handle1 = BufWriter::new(MockStdout::new());
 
-            config.opt.choice[0].print_choice(
-                &String::from("rust is pretty cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(&String::from("rust is pretty cool"), &config, &mut handle)
+                .unwrap();
 
             assert_eq!(
                 String::from("cool"),
                 MockStdout::str_from_buf_writer(handle)
             );
 
-            config.opt.choice[1].print_choice(
-                &String::from("rust is pretty cool"),
-                &config,
-                &mut handle1,
-            );
+            config.opt.choice[1]
+                .print_choice(&String::from("rust is pretty cool"), &config, &mut handle1)
+                .unwrap();
 
             assert_eq!(String::from("is"), MockStdout::str_from_buf_writer(handle1));
         }
@@ -297,11 +714,9 @@ mod tests {
         fn print_1_to_3_exclusive() {
             let config = Config::from_iter(vec!["choose", "1:3", "-x"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust is pretty cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(&String::from("rust is pretty cool"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("is pretty"),
                 MockStdout::str_from_buf_writer(handle)
@@ -312,11 +727,9 @@ mod tests {
         fn print_1_to_3() {
             let config = Config::from_iter(vec!["choose", "1:3"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust is pretty cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(&String::from("rust is pretty cool"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("is pretty cool"),
                 MockStdout::str_from_buf_writer(handle)
@@ -327,11 +740,9 @@ mod tests {
         fn print_1_to_3_separated_by_hashtag() {
             let config = Config::from_iter(vec!["choose", "1:3", "-f", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust#is#pretty#cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(&String::from("rust#is#pretty#cool"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("is pretty cool"),
                 MockStdout::str_from_buf_writer(handle)
@@ -342,11 +753,13 @@ mod tests {
         fn print_1_to_3_separated_by_varying_multiple_hashtag_exclusive() {
             let config = Config::from_iter(vec!["choose", "1:3", "-f", "#", "-x"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust##is###pretty####cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust##is###pretty####cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("is pretty"),
                 MockStdout::str_from_buf_writer(handle)
@@ -357,11 +770,13 @@ mod tests {
         fn print_1_to_3_separated_by_varying_multiple_hashtag() {
             let config = Config::from_iter(vec!["choose", "1:3", "-f", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust##is###pretty####cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust##is###pretty####cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("is pretty cool"),
                 MockStdout::str_from_buf_writer(handle)
@@ -372,11 +787,13 @@ mod tests {
         fn print_1_to_3_separated_by_regex_group_vowels_exclusive() {
             let config = Config::from_iter(vec!["choose", "1:3", "-f", "[aeiou]", "-x"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("the quick brown fox jumped over the lazy dog"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("the quick brown fox jumped over the lazy dog"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from(" q ck br"),
                 MockStdout::str_from_buf_writer(handle)
@@ -387,11 +804,13 @@ mod tests {
         fn print_1_to_3_separated_by_regex_group_vowels() {
             let config = Config::from_iter(vec!["choose", "1:3", "-f", "[aeiou]"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("the quick brown fox jumped over the lazy dog"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("the quick brown fox jumped over the lazy dog"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from(" q ck br wn f"),
                 MockStdout::str_from_buf_writer(handle)
@@ -402,11 +821,13 @@ mod tests {
         fn print_3_to_1() {
             let config = Config::from_iter(vec!["choose", "3:1"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("pretty is lang"),
                 MockStdout::str_from_buf_writer(handle)
@@ -417,11 +838,13 @@ mod tests {
         fn print_3_to_1_exclusive() {
             let config = Config::from_iter(vec!["choose", "3:1", "-x"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("is lang"),
                 MockStdout::str_from_buf_writer(handle)
@@ -432,11 +855,13 @@ mod tests {
         fn print_1_to_3_nonexistant_field_separator() {
             let config = Config::from_iter(vec!["choose", "1:3", "-f", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(String::from(""), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -444,11 +869,13 @@ mod tests {
         fn print_0_nonexistant_field_separator() {
             let config = Config::from_iter(vec!["choose", "0", "-f", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("rust lang is pretty darn cool"),
                 MockStdout::str_from_buf_writer(handle)
@@ -459,11 +886,13 @@ mod tests {
         fn print_0_to_3_nonexistant_field_separator() {
             let config = Config::from_iter(vec!["choose", "0:3", "-f", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("rust lang is pretty darn cool"),
                 MockStdout::str_from_buf_writer(handle)
@@ -474,11 +903,13 @@ mod tests {
         fn print_0_with_preceding_separator() {
             let config = Config::from_iter(vec!["choose", "0"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("   rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("   rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("rust"),
                 MockStdout::str_from_buf_writer(handle)
@@ -489,11 +920,13 @@ mod tests {
         fn print_neg3_to_neg1() {
             let config = Config::from_iter(vec!["choose", "-3:-1"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("pretty darn cool"),
                 MockStdout::str_from_buf_writer(handle)
@@ -504,11 +937,13 @@ mod tests {
         fn print_neg1_to_neg3() {
             let config = Config::from_iter(vec!["choose", "-1:-3"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("cool darn pretty"),
                 MockStdout::str_from_buf_writer(handle)
@@ -519,11 +954,13 @@ mod tests {
         fn print_neg2_to_end() {
             let config = Config::from_iter(vec!["choose", "-2:"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("darn cool"),
                 MockStdout::str_from_buf_writer(handle)
@@ -534,11 +971,13 @@ mod tests {
         fn print_start_to_neg3() {
             let config = Config::from_iter(vec!["choose", ":-3"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("rust lang is pretty"),
                 MockStdout::str_from_buf_writer(handle)
@@ -549,11 +988,13 @@ mod tests {
         fn print_1_to_neg3() {
             let config = Config::from_iter(vec!["choose", "1:-3"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("lang is pretty"),
                 MockStdout::str_from_buf_writer(handle)
@@ -564,11 +1005,13 @@ mod tests {
         fn print_5_to_neg3_empty() {
             let config = Config::from_iter(vec!["choose", "5:-3"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("rust lang is pretty darn cool"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("rust lang is pretty darn cool"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(String::from(""), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -576,7 +1019,9 @@ mod tests {
         fn print_0_to_2_greedy() {
             let config = Config::from_iter(vec!["choose", "0:2", "-f", ":"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a:b::c:::d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a:b::c:::d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("a b c"),
                 MockStdout::str_from_buf_writer(handle)
@@ -587,7 +1032,9 @@ mod tests {
         fn print_0_to_2_non_greedy() {
             let config = Config::from_iter(vec!["choose", "0:2", "-n", "-f", ":"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a:b::c:::d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a:b::c:::d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("a b"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -595,7 +1042,9 @@ mod tests {
         fn print_2_to_neg_1_non_greedy_negative() {
             let config = Config::from_iter(vec!["choose", "2:-1", "-n", "-f", ":"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a:b::c:::d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a:b::c:::d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("c d"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -603,7 +1052,9 @@ mod tests {
         fn print_2_to_0_non_greedy_reversed() {
             let config = Config::from_iter(vec!["choose", "2:0", "-n", "-f", ":"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a:b::c:::d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a:b::c:::d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("b a"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -611,7 +1062,9 @@ mod tests {
         fn print_neg_1_to_neg_3_non_greedy_negative_reversed() {
             let config = Config::from_iter(vec!["choose", "-1:-3", "-n", "-f", ":"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a:b::c:::d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a:b::c:::d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("d"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -619,7 +1072,9 @@ mod tests {
         fn print_1_to_3_with_output_field_separator() {
             let config = Config::from_iter(vec!["choose", "1:3", "-o", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a b c d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a b c d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("b#c#d"),
                 MockStdout::str_from_buf_writer(handle)
@@ -630,9 +1085,13 @@ mod tests {
         fn print_1_and_3_with_output_field_separator() {
             let config = Config::from_iter(vec!["choose", "1", "3", "-o", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a b c d"), &config, &mut handle);
-            handle.write(&config.output_separator).unwrap();
-            config.opt.choice[1].print_choice(&String::from("a b c d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a b c d"), &config, &mut handle)
+                .unwrap();
+            handle.write_all(&config.output_separator).unwrap();
+            config.opt.choice[1]
+                .print_choice(&String::from("a b c d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("b#d"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -640,11 +1099,13 @@ mod tests {
         fn print_2_to_4_with_output_field_separator() {
             let config = Config::from_iter(vec!["choose", "2:4", "-o", "%"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(
-                &String::from("Lorem ipsum dolor sit amet, consectetur"),
-                &config,
-                &mut handle,
-            );
+            config.opt.choice[0]
+                .print_choice(
+                    &String::from("Lorem ipsum dolor sit amet, consectetur"),
+                    &config,
+                    &mut handle,
+                )
+                .unwrap();
             assert_eq!(
                 String::from("dolor%sit%amet,"),
                 MockStdout::str_from_buf_writer(handle)
@@ -655,7 +1116,9 @@ mod tests {
         fn print_3_to_1_with_output_field_separator() {
             let config = Config::from_iter(vec!["choose", "3:1", "-o", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a b c d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a b c d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("d#c#b"),
                 MockStdout::str_from_buf_writer(handle)
@@ -666,7 +1129,9 @@ mod tests {
         fn print_0_to_neg_2_with_output_field_separator() {
             let config = Config::from_iter(vec!["choose", "0:-2", "-o", "#"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a b c d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a b c d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("a#b#c"),
                 MockStdout::str_from_buf_writer(handle)
@@ -677,7 +1142,9 @@ mod tests {
         fn print_0_to_2_with_empty_output_field_separator() {
             let config = Config::from_iter(vec!["choose", "0:2", "-o", ""]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("a b c d"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("a b c d"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("abc"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -685,7 +1152,9 @@ mod tests {
         fn print_0_to_2_character_wise() {
             let config = Config::from_iter(vec!["choose", "0:2", "-c"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("abc"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -693,7 +1162,9 @@ mod tests {
         fn print_2_to_end_character_wise() {
             let config = Config::from_iter(vec!["choose", "2:", "-c"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("cd"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -701,7 +1172,9 @@ mod tests {
         fn print_start_to_2_character_wise() {
             let config = Config::from_iter(vec!["choose", ":2", "-c"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("abc"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -709,15 +1182,49 @@ mod tests {
         fn print_0_to_2_character_wise_exclusive() {
             let config = Config::from_iter(vec!["choose", "0:2", "-c", "-x"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("ab"), MockStdout::str_from_buf_writer(handle));
         }
 
+        #[test]
+        fn print_2_to_2_character_wise_exclusive() {
+            let config = Config::from_iter(vec!["choose", "2:2", "-c", "-x"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from(""), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_start_to_0_character_wise_exclusive() {
+            let config = Config::from_iter(vec!["choose", ":0", "-c", "-x"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from(""), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_3_to_1_character_wise_exclusive() {
+            let config = Config::from_iter(vec!["choose", "3:1", "-c", "-x"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from("cb"), MockStdout::str_from_buf_writer(handle));
+        }
+
         #[test]
         fn print_0_to_2_character_wise_with_output_delimeter() {
             let config = Config::from_iter(vec!["choose", "0:2", "-c", "-o", ":"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("a:b:c"),
                 MockStdout::str_from_buf_writer(handle)
@@ -728,7 +1235,9 @@ mod tests {
         fn print_after_end_character_wise() {
             let config = Config::from_iter(vec!["choose", "0:9", "-c"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(
                 String::from("abcd"),
                 MockStdout::str_from_buf_writer(handle)
@@ -739,7 +1248,9 @@ mod tests {
         fn print_2_to_0_character_wise() {
             let config = Config::from_iter(vec!["choose", "2:0", "-c"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("cba"), MockStdout::str_from_buf_writer(handle));
         }
 
@@ -747,42 +1258,602 @@ mod tests {
         fn print_neg_2_to_end_character_wise() {
             let config = Config::from_iter(vec!["choose", "-2:", "-c"]);
             let mut handle = BufWriter::new(MockStdout::new());
-            config.opt.choice[0].print_choice(&String::from("abcd\n"), &config, &mut handle);
+            config.opt.choice[0]
+                .print_choice(&String::from("abcd\n"), &config, &mut handle)
+                .unwrap();
             assert_eq!(String::from("cd"), MockStdout::str_from_buf_writer(handle));
         }
     }
 
+    mod print_choice_negative_tests {
+        use super::*;
+
+        fn print(args: Vec<&str>, line: &str) -> String {
+            let config = Config::from_iter(args);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice(line, &config, &mut handle)
+                .unwrap();
+            MockStdout::str_from_buf_writer(handle)
+        }
+
+        #[test]
+        fn print_2_to_neg_2_long_line() {
+            assert_eq!(
+                String::from("c d e f g h i"),
+                print(vec!["choose", "2:-2"], "a b c d e f g h i j")
+            );
+        }
+
+        #[test]
+        fn print_neg_3_to_5_reversed() {
+            assert_eq!(
+                String::from("h g f"),
+                print(vec!["choose", "-3:5"], "a b c d e f g h i j")
+            );
+        }
+
+        #[test]
+        fn print_neg_3_to_5_forward() {
+            assert_eq!(
+                String::from("e f"),
+                print(vec!["choose", "-3:5"], "a b c d e f g")
+            );
+        }
+
+        #[test]
+        fn print_1_to_neg_3_single() {
+            assert_eq!(String::from("b"), print(vec!["choose", "1:-3"], "a b c d"));
+        }
+
+        #[test]
+        fn print_neg_9_out_of_range() {
+            assert_eq!(String::new(), print(vec!["choose", "-9"], "a b c"));
+        }
+
+        #[test]
+        fn print_neg_index_far_past_line() {
+            assert_eq!(
+                String::new(),
+                print(vec!["choose", "-10000000000"], "a b c")
+            );
+            assert_eq!(
+                String::new(),
+                print(vec!["choose", "-c", "-9223372036854775807"], "abc")
+            );
+        }
+
+        #[test]
+        fn print_neg_9_to_neg_2_clamped() {
+            assert_eq!(String::from("a b"), print(vec!["choose", "-9:-2"], "a b c"));
+        }
+
+        #[test]
+        fn print_neg_1_to_neg_4_character_wise() {
+            assert_eq!(
+                String::from("zyxw"),
+                print(vec!["choose", "-1:-4", "-c"], "abcdefwxyz")
+            );
+        }
+    }
+
+    mod print_choice_terminator_tests {
+        use super::*;
+
+        #[test]
+        fn print_0_to_9_character_wise_no_final_newline() {
+            let config = Config::from_iter(vec!["choose", "0:9", "-c"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("abcd"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_neg_1_character_wise_crlf() {
+            let config = Config::from_iter(vec!["choose", "-1", "-c", "-o", "|"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\r\n", &config, &mut handle)
+                .unwrap();
+            handle.write_all(&config.output_separator).unwrap();
+            assert_eq!(String::from("d|"), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_neg_1_crlf() {
+            let config = Config::from_iter(vec!["choose", "-1", "-o", "|"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a b c\r\n", &config, &mut handle)
+                .unwrap();
+            handle.write_all(&config.output_separator).unwrap();
+            assert_eq!(String::from("c|"), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_1_nul_terminated() {
+            let config = Config::from_iter(vec!["choose", "1", "--terminator", "\\0"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a b\n c\0", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from("b"), MockStdout::str_from_buf_writer(handle));
+        }
+    }
+
+    mod print_choice_grapheme_tests {
+        use super::*;
+
+        #[test]
+        fn print_0_to_1_combining_accents() {
+            let config = Config::from_iter(vec!["choose", "0:1", "--graphemes"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("e\u{301}a\u{308}o\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("e\u{301}a\u{308}"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_neg_1_flag() {
+            let config = Config::from_iter(vec!["choose", "-1", "--graphemes"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("ab\u{1f1eb}\u{1f1f7}\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("\u{1f1eb}\u{1f1f7}"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_2_to_0_zwj_sequence() {
+            let config = Config::from_iter(vec!["choose", "2:0", "--graphemes"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a\u{1f469}\u{200d}\u{1f4bb}b\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("b\u{1f469}\u{200d}\u{1f4bb}a"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_neg_2_to_neg_3_reversed() {
+            let config = Config::from_iter(vec!["choose", "-2:-3", "--graphemes"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("xe\u{301}a\u{308}o\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("a\u{308}e\u{301}"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+    }
+
+    mod print_choice_bytes_tests {
+        use super::*;
+
+        #[test]
+        fn print_2_to_2_bytes_exclusive() {
+            let config = Config::from_iter(vec!["choose", "2:2", "-b", "-x"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from(""), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_start_to_0_bytes_exclusive() {
+            let config = Config::from_iter(vec!["choose", ":0", "-b", "-x"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from(""), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_3_to_1_bytes_exclusive() {
+            let config = Config::from_iter(vec!["choose", "3:1", "-b", "-x"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from("cb"), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_0_to_2_bytes_with_output_separator() {
+            let config = Config::from_iter(vec!["choose", "0:2", "-b", "-o", ","]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcdef\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("a,b,c"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_2_to_0_bytes_with_choice_separator() {
+            let config = Config::from_iter(vec!["choose", "2:0/-", "-b"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcdef\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("c-b-a"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_utf8_boundary_bytes_with_output_separator() {
+            let config =
+                Config::from_iter(vec!["choose", "0:4", "-b", "--utf8-boundaries", "-o", ","]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("aéb€\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("a,é,b"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_0_to_2_bytes() {
+            let config = Config::from_iter(vec!["choose", "0:2", "-b"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from("abc"), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_neg_3_to_neg_2_bytes() {
+            let config = Config::from_iter(vec!["choose", "-3:-2", "-b"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from("bc"), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_2_to_0_bytes() {
+            let config = Config::from_iter(vec!["choose", "2:0", "-b"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::from("cba"), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_after_end_bytes() {
+            let config = Config::from_iter(vec!["choose", "4:9", "-b"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::new(), MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn print_1_to_3_bytes_utf8_boundaries() {
+            let config = Config::from_iter(vec!["choose", "1:3", "-b", "--utf8-boundaries"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a\u{e9}\u{e8}\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("\u{e9}"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_4_to_1_bytes_utf8_boundaries() {
+            let config = Config::from_iter(vec!["choose", "4:1", "-b", "--utf8-boundaries"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a\u{e9}\u{e8}\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("\u{e8}\u{e9}"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_0_bytes_utf8_boundaries_inside_char() {
+            let config = Config::from_iter(vec!["choose", "0", "-b", "--utf8-boundaries"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("\u{e9}\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(String::new(), MockStdout::str_from_buf_writer(handle));
+        }
+    }
+
+    mod print_choice_columns_tests {
+        use super::*;
+
+        #[test]
+        fn print_cols_straddle_pad_cluster_wider_than_2() {
+            let config = Config::from_iter(vec![
+                "choose",
+                "1:3",
+                "--cols",
+                "--straddle",
+                "pad",
+                "-o",
+                "|",
+            ]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a\u{1100}\u{1100}\u{1161}\n", &config, &mut handle)
+                .unwrap();
+            handle.write_all(&config.output_separator).unwrap();
+            assert_eq!(
+                String::from("   |"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_0_to_2_cols_with_output_separator() {
+            let config = Config::from_iter(vec!["choose", "0:2", "--cols", "-o", ","]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("a,b,c"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_2_to_0_cols_with_choice_separator() {
+            let config = Config::from_iter(vec!["choose", "2:0/-", "--cols"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("c-b-a"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_0_to_3_cols() {
+            let config = Config::from_iter(vec!["choose", "0:3", "--cols"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("\u{4e2d}\u{6587}ab\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("\u{4e2d}\u{6587}"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_0_to_2_cols_straddle_drop() {
+            let config = Config::from_iter(vec!["choose", "0:2", "--cols"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("\u{4e2d}\u{6587}ab\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("\u{4e2d}"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_0_to_2_cols_straddle_pad() {
+            let config = Config::from_iter(vec![
+                "choose",
+                "0:2",
+                "--cols",
+                "--straddle",
+                "pad",
+                "-o",
+                "|",
+            ]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("\u{4e2d}\u{6587}ab\n", &config, &mut handle)
+                .unwrap();
+            handle.write_all(&config.output_separator).unwrap();
+            assert_eq!(
+                String::from("\u{4e2d}| |"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_neg_2_to_end_cols() {
+            let config = Config::from_iter(vec!["choose", "-3:", "--cols"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("ab\u{4e2d}c\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("\u{4e2d}c"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn print_0_to_1_cols_combining_accent() {
+            let config = Config::from_iter(vec!["choose", "0:1", "--cols"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("e\u{301}xy\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                String::from("e\u{301}x"),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+    }
+
     mod is_reverse_range_tests {
         use super::*;
 
         #[test]
         fn is_field_reversed() {
             let config = Config::from_iter(vec!["choose", "0"]);
-            assert_eq!(false, config.opt.choice[0].is_reverse_range());
+            assert!(!config.opt.choice[0].is_reverse_range());
         }
 
         #[test]
         fn is_field_range_no_start_reversed() {
             let config = Config::from_iter(vec!["choose", ":2"]);
-            assert_eq!(false, config.opt.choice[0].is_reverse_range());
+            assert!(!config.opt.choice[0].is_reverse_range());
         }
 
         #[test]
         fn is_field_range_no_end_reversed() {
             let config = Config::from_iter(vec!["choose", "2:"]);
-            assert_eq!(false, config.opt.choice[0].is_reverse_range());
+            assert!(!config.opt.choice[0].is_reverse_range());
         }
 
         #[test]
         fn is_field_range_no_start_or_end_reversed() {
             let config = Config::from_iter(vec!["choose", ":"]);
-            assert_eq!(false, config.opt.choice[0].is_reverse_range());
+            assert!(!config.opt.choice[0].is_reverse_range());
         }
 
         #[test]
         fn is_reversed_field_range_reversed() {
             let config = Config::from_iter(vec!["choose", "4:2"]);
-            assert_eq!(true, config.opt.choice[0].is_reverse_range());
+            assert!(config.opt.choice[0].is_reverse_range());
+        }
+    }
+
+    mod print_choice_color_tests {
+        use super::*;
+        use crate::writer::PALETTE;
+
+        #[test]
+        fn color_items_not_separators() {
+            let config = Config::from_iter(vec!["choose", "0:1", "--color", "always"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a b c\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!(
+                format!("{0}a\x1b[0m {0}b\x1b[0m", PALETTE[0]),
+                MockStdout::str_from_buf_writer(handle)
+            );
+        }
+
+        #[test]
+        fn color_never() {
+            let config = Config::from_iter(vec!["choose", "0:1", "--color", "never"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("a b c\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!("a b", MockStdout::str_from_buf_writer(handle));
+        }
+    }
+
+    mod print_choice_transform_tests {
+        use super::*;
+
+        #[test]
+        fn transform_each_field() {
+            let config = Config::from_iter(vec!["choose", "0:1|upper|truncate:2"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abc def ghi\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!("AB DE", MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn global_transforms_run_first() {
+            let config = Config::from_iter(vec![
+                "choose",
+                "--transform",
+                "truncate:2",
+                "0|pad:3|replace:/ /_/",
+            ]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!("ab_", MockStdout::str_from_buf_writer(handle));
+        }
+
+        #[test]
+        fn transform_bytes_lossily() {
+            let config = Config::from_iter(vec!["choose", "-b", "0:3|upper"]);
+            let mut handle = BufWriter::new(MockStdout::new());
+            config.opt.choice[0]
+                .print_choice("abcd\n", &config, &mut handle)
+                .unwrap();
+            assert_eq!("ABCD", MockStdout::str_from_buf_writer(handle));
+        }
+    }
+
+    mod display_tests {
+        use super::*;
+
+        #[test]
+        fn display_round_trips() {
+            for arg in &[
+                "3",
+                "-1",
+                "1:4",
+                "2:",
+                "0:",
+                "5:-3",
+                "tags.-1",
+                "items.0:2",
+                "user.id",
+                "0:2/,",
+                "1:3/-|trim|upper",
+            ] {
+                let config = Config::from_iter(vec!["choose", "--json", arg]);
+                assert_eq!(*arg, config.opt.choice[0].to_string());
+            }
+        }
+
+        #[test]
+        fn display_exclusive_undoes_x() {
+            for (arg, shown) in &[(":0", "0:0"), ("1:4", "1:4"), ("3:1", "3:1"), ("2:", "2:")] {
+                let config = Config::from_iter(vec!["choose", "-x", arg]);
+                assert_eq!(*shown, config.opt.choice[0].exclusive().to_string());
+            }
+        }
+
+        #[test]
+        fn display_open_start_as_zero() {
+            let config = Config::from_iter(vec!["choose", ":3"]);
+            assert_eq!("0:3", config.opt.choice[0].to_string());
         }
     }
 }
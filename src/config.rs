@@ -1,57 +1,237 @@
 use regex::Regex;
-use std::num::ParseIntError;
-use std::process;
+use std::env;
+use std::ffi::OsString;
+use std::ops::Range;
+use std::str::FromStr;
 
 use crate::choice::Choice;
+use crate::error::Error;
+use crate::highlight::ANSI_MARKERS;
 use crate::opt::Opt;
+use crate::transform::Pipeline;
+use crate::writer::PALETTE;
 
-lazy_static! {
-    static ref PARSE_CHOICE_RE: Regex = Regex::new(r"^(-?\d*):(-?\d*)$").unwrap();
+/// What to do with a wide character that only partly falls inside a `--cols` range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Straddle {
+    Drop,
+    Pad,
+}
+
+impl FromStr for Straddle {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "drop" => Ok(Straddle::Drop),
+            "pad" => Ok(Straddle::Pad),
+            _ => Err(format!("unknown straddle policy: {}", src)),
+        }
+    }
+}
+
+/// The order in which `--group-by` prints groups.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    /// Ascending by key
+    Key,
+    /// Descending by number of records, then ascending by key
+    Count,
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "key" => Ok(SortBy::Key),
+            "count" => Ok(SortBy::Count),
+            _ => Err(format!("unknown group order: {}", src)),
+        }
+    }
+}
+
+/// Which earlier output records `--unique` compares each new one with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniqueMode {
+    /// Only the one before, in constant memory, like `uniq`
+    Adjacent,
+    /// Every one, each kept in memory
+    Global,
+    /// Every one, in a fixed size filter that rarely mistakes a new record for a repeat
+    Approx,
+}
+
+impl FromStr for UniqueMode {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "adjacent" => Ok(UniqueMode::Adjacent),
+            "global" => Ok(UniqueMode::Global),
+            "approx" => Ok(UniqueMode::Approx),
+            _ => Err(format!("unknown unique mode: {}", src)),
+        }
+    }
+}
+
+/// What `--transpose` turns into rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransposeMode {
+    /// Each item chosen from a record, with a blank line between records
+    Record,
+    /// Each column of the items chosen from all records
+    Table,
+}
+
+impl FromStr for TransposeMode {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "record" => Ok(TransposeMode::Record),
+            "table" => Ok(TransposeMode::Table),
+            _ => Err(format!("unknown transpose mode: {}", src)),
+        }
+    }
+}
+
+/// What `--transpose` prefixes each row with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Label {
+    /// The index of the item in its record
+    Index,
+    /// The item at the same place in the first record, which is not transposed itself
+    Header,
+}
+
+impl FromStr for Label {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "index" => Ok(Label::Index),
+            "header" => Ok(Label::Header),
+            _ => Err(format!("unknown label: {}", src)),
+        }
+    }
+}
+
+/// What to do with a record that cannot be read, such as one that is not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadErrorPolicy {
+    Skip,
+    Abort,
+}
+
+impl FromStr for ReadErrorPolicy {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "skip" => Ok(ReadErrorPolicy::Skip),
+            "abort" => Ok(ReadErrorPolicy::Abort),
+            _ => Err(format!("unknown read error policy: {}", src)),
+        }
+    }
+}
+
+/// When to color the output of each choice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorWhen {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "auto" => Ok(ColorWhen::Auto),
+            "always" => Ok(ColorWhen::Always),
+            "never" => Ok(ColorWhen::Never),
+            _ => Err(format!("unknown color setting: {}", src)),
+        }
+    }
+}
+
+impl ColorWhen {
+    /// Whether to color output, given whether it goes to a terminal and the value of `NO_COLOR`.
+    /// Like other tools, `auto` leaves output uncolored when `NO_COLOR` is set and not empty.
+    pub fn enabled(self, is_terminal: bool, no_color: Option<OsString>) -> bool {
+        match self {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => is_terminal && no_color.is_none_or(|v| v.is_empty()),
+        }
+    }
 }
 
 pub struct Config {
     pub opt: Opt,
     pub separator: Regex,
     pub output_separator: Box<[u8]>,
+    /// Separator between the output of each choice
+    pub outer_separator: Box<[u8]>,
+    /// Opening and closing markers put around selected parts of a record by `--highlight`
+    pub markers: (String, String),
+    /// Whether output is colored, as decided by `--color`
+    pub color: bool,
 }
 
 impl Config {
-    pub fn new(mut opt: Opt) -> Self {
+    /// Check and resolve `opt`. `stdout_is_terminal` decides whether `--color auto` colors output.
+    pub fn new(mut opt: Opt, stdout_is_terminal: bool) -> Result<Self, Error> {
+        if !opt.json {
+            if let Some(choice) = opt
+                .choice
+                .iter()
+                .chain(&opt.group_by)
+                .find(|c| c.has_path())
+            {
+                let path = choice.path.join(".");
+                return Err(Error::arg(&path, 0..path.len(), "a path requires --json"));
+            }
+        }
+
         if opt.exclusive {
-            for mut choice in &mut opt.choice {
+            for choice in opt.choice.iter_mut().chain(&mut opt.group_by) {
                 if choice.is_reverse_range() {
-                    choice.start = choice.start - 1;
+                    choice.start -= 1;
                 } else {
-                    choice.end = choice.end - 1;
+                    choice.end -= 1;
                 }
             }
         }
 
-        let separator = match Regex::new(match &opt.field_separator {
+        // JSON values are left uncolored for the programs that parse them, and the picker draws
+        // its own highlighting
+        let color = !opt.json
+            && !opt.interactive
+            && opt
+                .color
+                .enabled(stdout_is_terminal, env::var_os("NO_COLOR"));
+        if color {
+            for (choice, color) in opt.choice.iter_mut().zip(PALETTE.iter().cycle()) {
+                choice.color = Some(color);
+            }
+        }
+
+        if let Some(transforms) = &opt.transform {
+            for choice in opt.choice.iter_mut().chain(&mut opt.group_by) {
+                choice.transforms = transforms.then(&choice.transforms);
+            }
+        }
+
+        let separator = Regex::new(match &opt.field_separator {
             Some(s) => s,
             None => "[[:space:]]",
-        }) {
-            Ok(r) => r,
-            Err(e) => {
-                // Exit code of 2 means failed to compile field_separator regex
-                match e {
-                    regex::Error::Syntax(e) => {
-                        eprintln!("Syntax error compiling regular expression: {}", e);
-                        process::exit(2);
-                    }
-                    regex::Error::CompiledTooBig(e) => {
-                        eprintln!("Compiled regular expression too big: compiled size cannot exceed {} bytes", e);
-                        process::exit(2);
-                    }
-                    _ => {
-                        eprintln!("Error compiling regular expression: {}", e);
-                        process::exit(2);
-                    }
-                }
-            }
-        };
+        })
+        .map_err(Error::Regex)?;
 
-        let output_separator = match opt.character_wise {
+        let output_separator = match opt.character_wise || opt.graphemes || opt.bytes || opt.cols {
             false => match opt.output_field_separator.clone() {
                 Some(s) => s.into_boxed_str().into_boxed_bytes(),
                 None => Box::new([0x20; 1]),
@@ -62,50 +242,156 @@ impl Config {
             },
         };
 
-        Config {
+        let outer_separator = match &opt.outer_separator {
+            Some(s) => s.clone().into_boxed_str().into_boxed_bytes(),
+            None => output_separator.clone(),
+        };
+
+        let markers = match &opt.markers {
+            Some(markers) => markers.clone(),
+            None if color => (String::from(ANSI_MARKERS.0), String::from(ANSI_MARKERS.1)),
+            None => (String::from("[["), String::from("]]")),
+        };
+
+        Ok(Config {
             opt,
             separator,
             output_separator,
+            outer_separator,
+            markers,
+            color,
+        })
+    }
+
+    /// Split `line` into its content and the record terminator it ended with, which is empty when
+    /// the final line of the input has none. A carriage return before a newline terminator is
+    /// treated as part of the terminator.
+    pub fn split_terminator<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        let end = self.split_terminator_bytes(line.as_bytes()).0.len();
+        line.split_at(end)
+    }
+
+    /// Split `line`, which need not be valid UTF-8, like `split_terminator`.
+    pub fn split_terminator_bytes<'a>(&self, line: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+        let mut end = line.len();
+        if line.last() == Some(&self.opt.terminator) {
+            end -= 1;
+            if self.opt.terminator == b'\n' && line[..end].ends_with(b"\r") {
+                end -= 1;
+            }
         }
+        line.split_at(end)
     }
 
-    pub fn parse_choice(src: &str) -> Result<Choice, ParseIntError> {
-        let cap = match PARSE_CHOICE_RE.captures_iter(src).next() {
-            Some(v) => v,
-            None => match src.parse() {
-                Ok(x) => return Ok(Choice::new(x, x)),
-                Err(e) => {
-                    eprintln!("failed to parse choice argument: {}", src);
-                    return Err(e);
-                }
-            },
-        };
+    /// Split `line` into fields on the field separator, dropping the empty fields between
+    /// adjacent separators unless they are non-greedy.
+    pub fn split_fields<'a>(&'a self, line: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.separator
+            .split(line)
+            .filter(move |s| !s.is_empty() || self.opt.non_greedy)
+    }
 
-        let start = if cap[1].is_empty() {
-            0
-        } else {
-            match cap[1].parse() {
-                Ok(x) => x,
-                Err(e) => {
-                    eprintln!("failed to parse range start: {}", &cap[1]);
-                    return Err(e);
-                }
+    pub fn parse_choice(src: &str) -> Result<Choice, Error> {
+        let (spec_end, transforms) = match src.find('|') {
+            Some(i) => (i, Pipeline::parse_from(src, i + 1)?),
+            None => (src.len(), Pipeline::default()),
+        };
+        let (end, separator) = match src[..spec_end].find('/') {
+            Some(i) => (i, Some(String::from(&src[i + 1..spec_end]))),
+            None => (spec_end, None),
+        };
+        let choice = match src[..end].rfind('.') {
+            Some(dot) => Config::parse_path_choice(src, dot, end)?,
+            None if Config::is_bare_key(&src[..end]) => {
+                return Err(Error::arg(
+                    src,
+                    0..end,
+                    format!(
+                        "expected an index or range; a JSON key needs a leading `.`, as in `.{}`",
+                        &src[..end]
+                    ),
+                ))
             }
+            None => Config::parse_range(src, 0..end)?,
         };
+        Ok(choice.with_separator(separator).with_transforms(transforms))
+    }
 
-        let end = if cap[2].is_empty() {
-            isize::max_value()
-        } else {
-            match cap[2].parse() {
-                Ok(x) => x,
-                Err(e) => {
-                    eprintln!("failed to parse range end: {}", &cap[2]);
-                    return Err(e);
-                }
+    /// Whether `spec` is a word that could only have been meant as a JSON key, like `name`, rather
+    /// than a mistyped index or range.
+    fn is_bare_key(spec: &str) -> bool {
+        !spec.contains(':') && spec.starts_with(|c: char| c.is_alphabetic() || c == '_')
+    }
+
+    /// Parse the index or range in `src[span]`, pointing any error at the part of `src` at fault.
+    fn parse_range(src: &str, span: Range<usize>) -> Result<Choice, Error> {
+        match src[span.clone()].find(':') {
+            Some(i) => {
+                let colon = span.start + i;
+                let start = Config::parse_index(src, span.start..colon, Some(0))?;
+                let end = Config::parse_index(src, colon + 1..span.end, Some(isize::MAX))?;
+                Ok(Choice::new(start, end))
             }
-        };
+            None => {
+                let x = Config::parse_index(src, span, None)?;
+                Ok(Choice::new(x, x))
+            }
+        }
+    }
+
+    /// Parse the index in `src[span]`, which may only be empty if there is a `default`.
+    fn parse_index(src: &str, span: Range<usize>, default: Option<isize>) -> Result<isize, Error> {
+        let index = &src[span.clone()];
+        match default {
+            Some(x) if index.is_empty() => Ok(x),
+            _ => index.parse().map_err(|e| Error::arg(src, span, e)),
+        }
+    }
+
+    /// Parse the choice in `src[..end]` whose last `.` is at `dot`, which is a path optionally
+    /// ending in a range of array elements.
+    fn parse_path_choice(src: &str, dot: usize, end: usize) -> Result<Choice, Error> {
+        let mut path: Vec<String> = src[..dot]
+            .split('.')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect();
+        let last = &src[dot + 1..end];
+
+        if last.contains(':') {
+            return Ok(Config::parse_range(src, dot + 1..end)?.with_path(path));
+        }
+
+        if !last.is_empty() {
+            path.push(String::from(last));
+        }
+        Ok(Choice::from_path(path))
+    }
+
+    pub fn parse_terminator(src: &str) -> Result<u8, Error> {
+        match src {
+            "\\0" => Ok(b'\0'),
+            "\\n" => Ok(b'\n'),
+            "\\r" => Ok(b'\r'),
+            "\\t" => Ok(b'\t'),
+            _ if src.len() == 1 && src.is_ascii() => Ok(src.as_bytes()[0]),
+            _ => Err(Error::arg(
+                src,
+                0..src.len(),
+                "terminator must be a single ASCII character or one of \\0, \\n, \\r, \\t",
+            )),
+        }
+    }
 
-        return Ok(Choice::new(start, end));
+    pub fn parse_markers(src: &str) -> Result<(String, String), Error> {
+        match src.find("...") {
+            Some(i) => Ok((String::from(&src[..i]), String::from(&src[i + 3..]))),
+            None => Err(Error::arg(
+                src,
+                0..src.len(),
+                "markers must be an opening and closing marker either side of `...`",
+            )),
+        }
     }
 
     pub fn parse_output_field_separator(src: &str) -> String {
@@ -117,6 +403,108 @@ impl Config {
 mod tests {
     use super::*;
 
+    mod split_terminator_tests {
+        use super::*;
+
+        #[test]
+        fn split_lf() {
+            assert_eq!(
+                ("a b", "\n"),
+                Config::from_iter(vec!["choose", "0"]).split_terminator("a b\n")
+            );
+        }
+
+        #[test]
+        fn split_crlf() {
+            assert_eq!(
+                ("a b", "\r\n"),
+                Config::from_iter(vec!["choose", "0"]).split_terminator("a b\r\n")
+            );
+        }
+
+        #[test]
+        fn split_missing_final_newline() {
+            assert_eq!(
+                ("a b", ""),
+                Config::from_iter(vec!["choose", "0"]).split_terminator("a b")
+            );
+        }
+
+        #[test]
+        fn split_lone_cr_is_content() {
+            assert_eq!(
+                ("a b\r", ""),
+                Config::from_iter(vec!["choose", "0"]).split_terminator("a b\r")
+            );
+        }
+
+        #[test]
+        fn split_nul() {
+            assert_eq!(
+                ("a\r", "\0"),
+                Config::from_iter(vec!["choose", "0", "--terminator", "\\0"])
+                    .split_terminator("a\r\0")
+            );
+        }
+    }
+
+    mod parse_terminator_tests {
+        use super::*;
+
+        #[test]
+        fn parse_escaped_terminator() {
+            assert_eq!(b'\0', Config::parse_terminator("\\0").unwrap());
+            assert_eq!(b'\t', Config::parse_terminator("\\t").unwrap());
+        }
+
+        #[test]
+        fn parse_single_character_terminator() {
+            assert_eq!(b';', Config::parse_terminator(";").unwrap());
+        }
+
+        #[test]
+        fn parse_bad_terminator() {
+            assert!(Config::parse_terminator("ab").is_err());
+        }
+    }
+
+    mod color_tests {
+        use super::*;
+
+        #[test]
+        fn color_when_enabled() {
+            assert!(ColorWhen::Auto.enabled(true, None));
+            assert!(ColorWhen::Auto.enabled(true, Some(OsString::new())));
+            assert!(!ColorWhen::Auto.enabled(true, Some(OsString::from("1"))));
+            assert!(!ColorWhen::Auto.enabled(false, None));
+            assert!(ColorWhen::Always.enabled(false, Some(OsString::from("1"))));
+            assert!(!ColorWhen::Never.enabled(true, None));
+        }
+
+        #[test]
+        fn color_cycles_palette() {
+            let mut args = vec!["choose", "--color", "always"];
+            let indices: Vec<String> = (0..=PALETTE.len()).map(|i| i.to_string()).collect();
+            args.extend(indices.iter().map(String::as_str));
+            let config = Config::from_iter(args);
+            assert_eq!(Some(PALETTE[1]), config.opt.choice[1].color);
+            assert_eq!(Some(PALETTE[0]), config.opt.choice[PALETTE.len()].color);
+        }
+
+        #[test]
+        fn color_never_for_json() {
+            let config = Config::from_iter(vec!["choose", "0", "--json", "--color", "always"]);
+            assert_eq!(None, config.opt.choice[0].color);
+        }
+
+        #[test]
+        fn color_auto_off_without_terminal() {
+            let config = Config::from_iter(vec!["choose", "0", "--color", "auto"]);
+            assert!(!config.color);
+            assert_eq!(None, config.opt.choice[0].color);
+        }
+    }
+
     mod parse_choice_tests {
         use super::*;
 
@@ -141,7 +529,7 @@ mod tests {
         #[test]
         fn parse_none_terminated_range() {
             let result = Config::parse_choice("5:").unwrap();
-            assert_eq!((5, isize::max_value()), (result.start, result.end))
+            assert_eq!((5, isize::MAX), (result.start, result.end))
         }
 
         #[test]
@@ -159,7 +547,7 @@ mod tests {
         #[test]
         fn parse_neg_started_none_ended() {
             let result = Config::parse_choice("-3:").unwrap();
-            assert_eq!((-3, isize::max_value()), (result.start, result.end))
+            assert_eq!((-3, isize::MAX), (result.start, result.end))
         }
 
         #[test]
@@ -183,7 +571,7 @@ mod tests {
         #[test]
         fn parse_beginning_to_end_range() {
             let result = Config::parse_choice(":").unwrap();
-            assert_eq!((0, isize::max_value()), (result.start, result.end))
+            assert_eq!((0, isize::MAX), (result.start, result.end))
         }
 
         #[test]
@@ -191,9 +579,101 @@ mod tests {
             assert!(Config::parse_choice("d").is_err());
         }
 
+        #[test]
+        fn parse_bare_key_suggests_dot() {
+            match Config::parse_choice("name") {
+                Err(Error::Arg { span, reason, .. }) => {
+                    assert_eq!(0..4, span);
+                    assert!(reason.contains("`.name`"), "{}", reason);
+                }
+                _ => panic!("expected an argument error"),
+            }
+        }
+
         #[test]
         fn parse_bad_range() {
             assert!(Config::parse_choice("d:i").is_err());
         }
+
+        #[test]
+        fn parse_path_key() {
+            let result = Config::parse_choice("user.id").unwrap();
+            assert_eq!(vec!["user", "id"], result.path);
+            assert!(result.has_path());
+        }
+
+        #[test]
+        fn parse_path_index() {
+            let result = Config::parse_choice("tags.-1").unwrap();
+            assert_eq!(vec!["tags", "-1"], result.path);
+        }
+
+        #[test]
+        fn parse_path_range() {
+            let result = Config::parse_choice("items.0:2").unwrap();
+            assert_eq!(vec!["items"], result.path);
+            assert_eq!((0, 2), (result.start, result.end))
+        }
+
+        #[test]
+        fn parse_root_key() {
+            let result = Config::parse_choice(".name").unwrap();
+            assert_eq!(vec!["name"], result.path);
+        }
+
+        #[test]
+        fn parse_path_bad_range() {
+            assert!(Config::parse_choice("items.0:x").is_err());
+        }
+
+        #[test]
+        fn parse_bad_range_end_points_at_it() {
+            match Config::parse_choice("3:x") {
+                Err(Error::Arg { span, .. }) => assert_eq!(2..3, span),
+                _ => panic!("expected an argument error"),
+            }
+        }
+
+        #[test]
+        fn parse_bad_path_range_points_into_path() {
+            match Config::parse_choice("items.x:2") {
+                Err(Error::Arg { arg, span, .. }) => {
+                    assert_eq!("items.x:2", arg);
+                    assert_eq!(6..7, span);
+                }
+                _ => panic!("expected an argument error"),
+            }
+        }
+
+        #[test]
+        fn parse_separator_override() {
+            let result = Config::parse_choice("0:2/, ").unwrap();
+            assert_eq!((0, 2), (result.start, result.end));
+            assert_eq!(Some(String::from(", ")), result.separator);
+        }
+
+        #[test]
+        fn parse_separator_override_may_hold_dots_and_colons() {
+            let result = Config::parse_choice("items.0:2/.:").unwrap();
+            assert_eq!(vec!["items"], result.path);
+            assert_eq!(Some(String::from(".:")), result.separator);
+        }
+
+        #[test]
+        fn parse_empty_separator_override() {
+            let result = Config::parse_choice("3/").unwrap();
+            assert_eq!(Some(String::new()), result.separator);
+        }
+
+        #[test]
+        fn parse_bad_range_before_separator_points_at_it() {
+            match Config::parse_choice("x:2/,") {
+                Err(Error::Arg { arg, span, .. }) => {
+                    assert_eq!("x:2/,", arg);
+                    assert_eq!(0..1, span);
+                }
+                _ => panic!("expected an argument error"),
+            }
+        }
     }
 }
@@ -0,0 +1,207 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::choice::Choice;
+use crate::config::Config;
+
+/// Number of leading records that `-d` explains.
+const RECORDS: usize = 5;
+
+/// With `-d`, print how the arguments were understood to stderr.
+pub fn print_config(config: &Config) {
+    if !config.opt.debug {
+        return;
+    }
+
+    let unit = unit(config);
+    eprintln!("debug: choosing {}s", unit);
+    if unit == "field" {
+        eprintln!(
+            "debug: separator: /{}/{}",
+            config.separator,
+            if config.opt.non_greedy {
+                " (non-greedy)"
+            } else {
+                ""
+            }
+        );
+    }
+    eprintln!(
+        "debug: output separator: {:?}, outer separator: {:?}, terminator: {:?}",
+        String::from_utf8_lossy(&config.output_separator),
+        String::from_utf8_lossy(&config.outer_separator),
+        config.opt.terminator as char
+    );
+    for (i, choice) in config.opt.choice.iter().enumerate() {
+        eprintln!("debug: choice {}: {}", i, describe(choice, config));
+    }
+    for (i, choice) in config.opt.group_by.iter().enumerate() {
+        eprintln!("debug: group key {}: {}", i, describe(choice, config));
+    }
+}
+
+/// With `-d`, print how `record`, found on `line` of the input, is split and what each choice
+/// selects from it, for the first few records only.
+pub fn print_record(line: usize, record: &[u8], config: &Config) {
+    if !config.opt.debug || line > RECORDS {
+        return;
+    }
+
+    let (bytes, _) = config.split_terminator_bytes(record);
+    let record = String::from_utf8_lossy(bytes);
+    let len = if config.opt.json {
+        eprintln!("debug: record {}: {}", line, record);
+        return;
+    } else if config.opt.bytes {
+        bytes.len()
+    } else if config.opt.cols {
+        record.width()
+    } else if config.opt.character_wise {
+        record.chars().count()
+    } else if config.opt.graphemes {
+        record.graphemes(true).count()
+    } else {
+        let fields: Vec<&str> = config.split_fields(&record).collect();
+        let listed: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| format!("[{}] {:?}", i, field))
+            .collect();
+        eprintln!("debug: record {}: {}", line, listed.join(" "));
+        fields.len()
+    };
+
+    let plural = if len == 1 { "" } else { "s" };
+    eprintln!("debug: record {}: {} {}{}", line, len, unit(config), plural);
+    for (i, choice) in config.opt.choice.iter().enumerate() {
+        eprintln!(
+            "debug: record {}: choice {} {}",
+            line,
+            i,
+            selection(choice, len)
+        );
+    }
+}
+
+/// What `choice` selects from a record of `len` items, resolved by the same bounds that printing
+/// the choice uses.
+fn selection(choice: &Choice, len: usize) -> String {
+    match choice.get_bounds(len) {
+        Some((lo, hi, reversed)) => format!(
+            "selects {}..={}{}",
+            lo,
+            hi,
+            if reversed { " reversed" } else { "" }
+        ),
+        None => String::from("selects nothing"),
+    }
+}
+
+/// With `-d`, print how many output records `--unique` left out.
+pub fn print_suppressed(count: usize, config: &Config) {
+    if config.opt.debug {
+        let plural = if count == 1 { "" } else { "s" };
+        eprintln!("debug: left out {} repeated record{}", count, plural);
+    }
+}
+
+fn unit(config: &Config) -> &'static str {
+    if config.opt.json {
+        "JSON value"
+    } else if config.opt.bytes {
+        "byte"
+    } else if config.opt.cols {
+        "column"
+    } else if config.opt.character_wise {
+        "character"
+    } else if config.opt.graphemes {
+        "grapheme"
+    } else {
+        "field"
+    }
+}
+
+fn describe(choice: &Choice, config: &Config) -> String {
+    let mut description = String::new();
+    if !choice.path.is_empty() {
+        description.push_str(&format!("path {}, ", choice.path.join(".")));
+    }
+    if choice.has_path() && choice.start == 0 && choice.end == isize::MAX {
+        description.push_str("whole value");
+        return description;
+    }
+    description.push_str(&format!(
+        "start {}, end {}{}",
+        choice.start,
+        choice.end,
+        if config.opt.exclusive {
+            " after --exclusive adjustment"
+        } else {
+            ""
+        }
+    ));
+    if choice.is_reverse_range() {
+        description.push_str(", reversed");
+    }
+    if choice.has_negative_index() {
+        description.push_str(", negative index");
+    }
+    if let Some(separator) = &choice.separator {
+        description.push_str(&format!(", separator {:?}", separator));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufWriter;
+
+    #[test]
+    fn describe_exclusive_reversed() {
+        let config = Config::from_iter(vec!["choose", "-x", "3:1"]);
+        assert_eq!(
+            "start 2, end 1 after --exclusive adjustment, reversed",
+            describe(&config.opt.choice[0], &config)
+        );
+    }
+
+    #[test]
+    fn describe_negative() {
+        let config = Config::from_iter(vec!["choose", "-2:"]);
+        assert_eq!(
+            format!("start -2, end {}, negative index", isize::MAX),
+            describe(&config.opt.choice[0], &config)
+        );
+    }
+
+    #[test]
+    fn selection_matches_output_with_exclusive() {
+        let line = "a b c d\n";
+        for (choice, selected, printed) in [
+            ("2:2", "selects nothing", ""),
+            (":0", "selects nothing", ""),
+            ("1:3", "selects 1..=2", "b c"),
+            ("3:1", "selects 1..=2 reversed", "c b"),
+            ("-1:-3", "selects 1..=2 reversed", "c b"),
+        ] {
+            let config = Config::from_iter(vec!["choose", "-x", choice]);
+            let mut handle = BufWriter::new(Vec::new());
+            config.opt.choice[0]
+                .print_choice(line, &config, &mut handle)
+                .unwrap();
+            let output = handle.into_inner().unwrap();
+            assert_eq!(selected, selection(&config.opt.choice[0], 4), "{}", choice);
+            assert_eq!(printed.as_bytes(), &output[..], "{}", choice);
+        }
+    }
+
+    #[test]
+    fn describe_json_path() {
+        let config = Config::from_iter(vec!["choose", "--json", "user.name"]);
+        assert_eq!(
+            "path user.name, whole value",
+            describe(&config.opt.choice[0], &config)
+        );
+    }
+}
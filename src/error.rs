@@ -0,0 +1,134 @@
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Everything that can make `choose` fail. Each kind of failure has its own exit code:
+///
+/// | code | meaning                                         |
+/// |------|-------------------------------------------------|
+/// | 1    | an argument could not be parsed                 |
+/// | 2    | the field separator is not a valid regex        |
+/// | 3    | the input file could not be opened              |
+/// | 4    | output could not be written                     |
+/// | 5    | some input records could not be read            |
+/// | 6    | the terminal could not be used by --interactive |
+/// | 141  | the reader of the output went away (SIGPIPE)    |
+#[derive(Debug)]
+pub enum Error {
+    /// An argument that could not be parsed, with the byte span of the part of it at fault
+    Arg {
+        arg: String,
+        span: Range<usize>,
+        reason: String,
+    },
+    Regex(regex::Error),
+    Open {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A record of the input named by `source` that could not be read
+    Read {
+        source: String,
+        line: usize,
+        reason: String,
+    },
+    /// The number of records that could not be read, each already reported as a `Read` error
+    Unread(usize),
+    Write(io::Error),
+    Terminal(io::Error),
+}
+
+impl Error {
+    /// An error in the part of `arg` covered by `span`.
+    pub fn arg<S: ToString>(arg: &str, span: Range<usize>, reason: S) -> Self {
+        Error::Arg {
+            arg: String::from(arg),
+            span,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Arg { .. } => 1,
+            Error::Regex(_) => 2,
+            Error::Open { .. } => 3,
+            Error::Write(e) if e.kind() == io::ErrorKind::BrokenPipe => 141,
+            Error::Write(_) => 4,
+            Error::Read { .. } | Error::Unread(_) => 5,
+            Error::Terminal(_) => 6,
+        }
+    }
+
+    /// Whether the error is worth telling the user about; a reader of the output that went away
+    /// already knows.
+    pub fn is_quiet(&self) -> bool {
+        self.exit_code() == 141
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Arg { arg, span, reason } => {
+                writeln!(f, "invalid argument `{}`: {}", arg, reason)?;
+                writeln!(f, "    {}", arg)?;
+                let width = arg[span.clone()].chars().count().max(1);
+                let offset = arg[..span.start].chars().count();
+                write!(f, "    {}{}", " ".repeat(offset), "^".repeat(width))
+            }
+            Error::Regex(regex::Error::Syntax(e)) => {
+                write!(f, "Syntax error compiling regular expression: {}", e)
+            }
+            Error::Regex(regex::Error::CompiledTooBig(e)) => write!(
+                f,
+                "Compiled regular expression too big: compiled size cannot exceed {} bytes",
+                e
+            ),
+            Error::Regex(e) => write!(f, "Error compiling regular expression: {}", e),
+            Error::Open { path, source } => {
+                write!(f, "Failed to open file {}: {}", path.display(), source)
+            }
+            Error::Read {
+                source,
+                line,
+                reason,
+            } => write!(f, "{}:{}: failed to read record: {}", source, line, reason),
+            Error::Unread(1) => write!(f, "1 record could not be read"),
+            Error::Unread(n) => write!(f, "{} records could not be read", n),
+            Error::Write(e) => write!(f, "Failed to write to output: {}", e),
+            Error::Terminal(e) => write!(f, "Failed to use the terminal: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arg_caret_under_span() {
+        let e = Error::arg("3:x", 2..3, "invalid digit found in string");
+        assert_eq!(
+            "invalid argument `3:x`: invalid digit found in string\n    3:x\n      ^",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn arg_caret_under_empty_span() {
+        let e = Error::arg("3:", 2..2, "missing index");
+        assert!(e.to_string().ends_with("\n    3:\n      ^"));
+    }
+
+    #[test]
+    fn broken_pipe_is_quiet() {
+        let e = Error::Write(io::Error::from(io::ErrorKind::BrokenPipe));
+        assert_eq!(141, e.exit_code());
+        assert!(e.is_quiet());
+        assert!(!Error::Write(io::Error::from(io::ErrorKind::Other)).is_quiet());
+    }
+}
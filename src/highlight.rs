@@ -0,0 +1,103 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::config::Config;
+
+/// Markers used by `--highlight` when output is colored: bold and underlined.
+pub const ANSI_MARKERS: (&str, &str) = ("\x1b[1;4m", "\x1b[0m");
+
+/// Write all of `line`, which has no terminator, with the parts that any choice selects between
+/// the configured markers. Overlapping or adjacent parts share one pair of markers.
+pub fn print_record<W: Write>(line: &str, config: &Config, handle: &mut W) -> io::Result<()> {
+    let mut spans: Vec<Range<usize>> = config
+        .opt
+        .choice
+        .iter()
+        .flat_map(|choice| choice.spans(line, config))
+        .collect();
+    spans.sort_by_key(|span| span.start);
+
+    let (open, close) = &config.markers;
+    let line = line.as_bytes();
+    let mut written = 0;
+    let mut spans = spans.into_iter().peekable();
+    while let Some(mut span) = spans.next() {
+        while let Some(next) = spans.next_if(|next| next.start <= span.end) {
+            span.end = span.end.max(next.end);
+        }
+        handle.write_all(&line[written..span.start])?;
+        handle.write_all(open.as_bytes())?;
+        handle.write_all(&line[span.clone()])?;
+        handle.write_all(close.as_bytes())?;
+        written = span.end;
+    }
+    handle.write_all(&line[written..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight(args: &[&str], line: &str) -> String {
+        let mut argv = vec!["choose", "--highlight", "--color", "never"];
+        argv.extend_from_slice(args);
+        let config = Config::from_iter(argv);
+        let mut out = Vec::new();
+        print_record(line, &config, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn highlight_fields() {
+        assert_eq!("[[a]]  b [[c]] d", highlight(&["0", "2"], "a  b c d"));
+    }
+
+    #[test]
+    fn highlight_range_per_field() {
+        assert_eq!("a [[b]] [[c]] d", highlight(&["1:2"], "a b c d"));
+    }
+
+    #[test]
+    fn highlight_negative_and_reversed() {
+        assert_eq!("a [[b]] [[c]] d", highlight(&["-2:1"], "a b c d"));
+    }
+
+    #[test]
+    fn highlight_overlapping_choices_once() {
+        assert_eq!("[[a]] [[b]] c", highlight(&["0:1", "1", "0"], "a b c"));
+    }
+
+    #[test]
+    fn highlight_regex_separator() {
+        assert_eq!("a, [[b]];c", highlight(&["-f", "[,;] ?", "1"], "a, b;c"));
+    }
+
+    #[test]
+    fn highlight_characters_merged() {
+        assert_eq!("h[[éll]]o", highlight(&["-c", "1:3"], "héllo"));
+    }
+
+    #[test]
+    fn highlight_graphemes() {
+        assert_eq!("e\u{301}[[x]]", highlight(&["-g", "1"], "e\u{301}x"));
+    }
+
+    #[test]
+    fn highlight_nothing() {
+        assert_eq!("a b", highlight(&["5"], "a b"));
+    }
+
+    #[test]
+    fn highlight_custom_markers() {
+        assert_eq!("<a> b", highlight(&["--markers", "<...>", "0"], "a b"));
+    }
+
+    #[test]
+    fn highlight_ansi_when_colored() {
+        let argv = vec!["choose", "--highlight", "--color", "always", "1"];
+        let config = Config::from_iter(argv);
+        let mut out = Vec::new();
+        print_record("a b", &config, &mut out).unwrap();
+        assert_eq!(b"a \x1b[1;4mb\x1b[0m".to_vec(), out);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, Read, Write};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::aggregate;
+use crate::config::Config;
+use crate::debug;
+use crate::json;
+use crate::reader::{self, ErrorLog};
+
+/// Width assumed for output that is not to a terminal.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Fewest columns a bar is scaled to, however little room the counts and keys leave.
+const MIN_BAR_WIDTH: usize = 10;
+
+/// Whether `--histogram` or `--top` was given.
+pub fn requested(config: &Config) -> bool {
+    config.opt.histogram || config.opt.top.is_some()
+}
+
+/// Count the records of `read` by what the choices select from them, then print each distinct
+/// selection with its count, from the most common to the least.
+pub fn run<R: Read, W: Write>(
+    read: R,
+    config: &Config,
+    handle: &mut W,
+    log: &mut ErrorLog,
+) -> io::Result<()> {
+    let mut counts: HashMap<Vec<String>, usize> = HashMap::new();
+    reader::for_each(read, config, log, |line, l| {
+        debug::print_record(line, l.as_bytes(), config);
+        let (record, _) = config.split_terminator(l);
+        let value = if config.opt.json {
+            Some(json::parse_record(record)?)
+        } else {
+            None
+        };
+        let key = aggregate::key(&config.opt.choice, record, value.as_ref(), config);
+        *counts.entry(key).or_insert(0) += 1;
+        Ok(())
+    })?;
+
+    let mut counts: Vec<(Vec<String>, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a, m), (b, n)| n.cmp(m).then_with(|| a.cmp(b)));
+    if let Some(top) = config.opt.top {
+        counts.truncate(top);
+    }
+    print(&counts, config, handle, terminal_width())
+}
+
+/// Write each count and its key, separated by the outer separator. With `--bars`, the counts and
+/// keys are aligned and followed by a bar, leaving the longest bar as much of `width` as is left.
+fn print<W: Write>(
+    counts: &[(Vec<String>, usize)],
+    config: &Config,
+    handle: &mut W,
+    width: usize,
+) -> io::Result<()> {
+    let separator = String::from_utf8_lossy(&config.outer_separator);
+    let keys: Vec<String> = counts.iter().map(|(key, _)| key.join(&separator)).collect();
+    let most = counts.first().map_or(0, |(_, n)| *n);
+    let count_width = most.to_string().len();
+    let key_width = keys.iter().map(|key| key.width()).max().unwrap_or(0);
+    let bar_width = width
+        .saturating_sub(count_width + separator.width() + key_width + 1)
+        .max(MIN_BAR_WIDTH);
+
+    for ((_, n), key) in counts.iter().zip(&keys) {
+        if config.opt.bars {
+            write!(handle, "{:>1$}", n, count_width)?;
+        } else {
+            write!(handle, "{}", n)?;
+        }
+        handle.write_all(separator.as_bytes())?;
+        handle.write_all(key.as_bytes())?;
+        if config.opt.bars {
+            let bar = (n * bar_width / most).max(1);
+            let pad = key_width - key.width() + 1;
+            write!(handle, "{:2$}{}", "", "#".repeat(bar), pad)?;
+        }
+        handle.write_all(&[config.opt.terminator])?;
+    }
+    Ok(())
+}
+
+/// Width of the terminal, from `COLUMNS` or the terminal that output goes to, if any.
+fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(stdout_width)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+#[cfg(unix)]
+fn stdout_width() -> Option<usize> {
+    // SAFETY: `winsize` is plain data filled in by the ioctl, which fails harmlessly if stdout is
+    // not a terminal.
+    let size = unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 {
+            return None;
+        }
+        size
+    };
+    Some(usize::from(size.ws_col)).filter(|&columns| columns > 0)
+}
+
+#[cfg(not(unix))]
+fn stdout_width() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(args: &[&str], input: &str) -> String {
+        let mut argv = vec!["choose"];
+        argv.extend_from_slice(args);
+        let config = Config::from_iter(argv);
+        let mut log = ErrorLog::new(&config);
+        let mut out = Vec::new();
+        run(input.as_bytes(), &config, &mut out, &mut log).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    const INPUT: &str = "GET /a 200\nGET /b 404\nPOST /a 200\nGET /a 500\nGET /a 200\n";
+
+    #[test]
+    fn histogram_most_common_first() {
+        assert_eq!("4 GET\n1 POST\n", histogram(&["--histogram", "0"], INPUT));
+    }
+
+    #[test]
+    fn histogram_ties_in_key_order() {
+        assert_eq!(
+            "3 200\n1 404\n1 500\n",
+            histogram(&["--histogram", "2"], INPUT)
+        );
+    }
+
+    #[test]
+    fn histogram_of_tuples() {
+        assert_eq!(
+            "3,/a,200\n1,/a,500\n",
+            histogram(&["--top", "2", "1", "2", "-o", ","], INPUT)
+        );
+    }
+
+    #[test]
+    fn histogram_of_reversed_range_in_print_order() {
+        assert_eq!("1 c b a\n", histogram(&["--histogram", "2:0"], "a b c\n"));
+    }
+
+    #[test]
+    fn histogram_skips_bad_json() {
+        let config = Config::from_iter(vec!["choose", "--histogram", "--json", ".a"]);
+        let mut log = ErrorLog::new(&config);
+        let mut out = Vec::new();
+        let input = "{\"a\":1}\nnot json\n{\"a\":1}\n";
+        run(input.as_bytes(), &config, &mut out, &mut log).unwrap();
+        assert_eq!("2 1\n", String::from_utf8(out).unwrap());
+        assert_eq!(5, log.finish().unwrap_err().exit_code());
+    }
+
+    #[test]
+    fn bars_scaled_to_width() {
+        let config = Config::from_iter(vec!["choose", "--histogram", "--bars", "0"]);
+        let counts = vec![
+            (vec![String::from("GET")], 40),
+            (vec![String::from("POST")], 10),
+        ];
+        let mut out = Vec::new();
+        print(&counts, &config, &mut out, 28).unwrap();
+        assert_eq!(
+            format!("40 GET  {}\n10 POST {}\n", "#".repeat(20), "#".repeat(5)),
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn bars_never_empty() {
+        let config = Config::from_iter(vec!["choose", "--histogram", "--bars", "0"]);
+        let counts = vec![
+            (vec![String::from("a")], 1000),
+            (vec![String::from("b")], 1),
+        ];
+        let mut out = Vec::new();
+        print(&counts, &config, &mut out, 20).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("   1 b #\n"));
+    }
+}
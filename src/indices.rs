@@ -0,0 +1,102 @@
+use std::io::{self, Read, Write};
+
+use crate::config::Config;
+use crate::reader::{self, ErrorLog};
+use crate::writer::{PALETTE, RESET};
+
+/// Number of leading records that `--show-indices` prints.
+const SAMPLE: usize = 10;
+
+/// Print the first `SAMPLE` records of `read` with their fields labelled by index.
+pub fn run<R: Read, W: Write>(
+    read: R,
+    config: &Config,
+    handle: &mut W,
+    log: &mut ErrorLog,
+) -> io::Result<()> {
+    for record in reader::sample(read, SAMPLE, config, log) {
+        print_record(&record, config, handle)?;
+    }
+    Ok(())
+}
+
+/// Write the fields of `line` as `choose` would split them, each preceded by its positive and
+/// negative index, e.g. `[0,-3]a  [1,-2]b  [2,-1]c`. With color, each field and its label are
+/// colored in turn from the palette that `--color` gives choices.
+pub fn print_record<W: Write>(line: &str, config: &Config, handle: &mut W) -> io::Result<()> {
+    let (line, _) = config.split_terminator(line);
+    let fields: Vec<&str> = config.split_fields(line).collect();
+    for ((i, field), color) in fields.iter().enumerate().zip(PALETTE.iter().cycle()) {
+        if i > 0 {
+            handle.write_all(b"  ")?;
+        }
+        if config.color {
+            handle.write_all(color.as_bytes())?;
+        }
+        write!(handle, "[{},-{}]{}", i, fields.len() - i, field)?;
+        if config.color {
+            handle.write_all(RESET)?;
+        }
+    }
+    handle.write_all(&[config.opt.terminator])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(line: &str, config: &Config) -> String {
+        let mut out = Vec::new();
+        print_record(line, config, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn label_fields() {
+        assert_eq!(
+            "[0,-3]a  [1,-2]b  [2,-1]c\n",
+            show(
+                "a  b c\n",
+                &Config::from_iter(vec!["choose", "--show-indices"])
+            )
+        );
+    }
+
+    #[test]
+    fn label_non_greedy_empty_fields() {
+        let config = Config::from_iter(vec!["choose", "--show-indices", "-n", "-f", ","]);
+        assert_eq!("[0,-3]a  [1,-2]  [2,-1]c\n", show("a,,c\n", &config));
+    }
+
+    #[test]
+    fn label_fields_in_color() {
+        let config = Config::from_iter(vec!["choose", "--show-indices", "--color", "always"]);
+        assert_eq!(
+            format!(
+                "{}[0,-2]a\x1b[0m  {}[1,-1]b\x1b[0m\n",
+                PALETTE[0], PALETTE[1]
+            ),
+            show("a b\n", &config)
+        );
+    }
+
+    #[test]
+    fn label_empty_record() {
+        assert_eq!(
+            "\n",
+            show("\n", &Config::from_iter(vec!["choose", "--show-indices"]))
+        );
+    }
+
+    #[test]
+    fn run_prints_sample() {
+        let config = Config::from_iter(vec!["choose", "--show-indices"]);
+        let input: String = (0..20).map(|i| format!("x {}\n", i)).collect();
+        let mut out = Vec::new();
+        let mut log = ErrorLog::new(&config);
+        run(input.as_bytes(), &config, &mut out, &mut log).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(SAMPLE, out.lines().count());
+        assert_eq!(Some("[0,-2]x  [1,-1]9"), out.lines().last());
+    }
+}
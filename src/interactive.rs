@@ -0,0 +1,546 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::choice::Choice;
+use crate::config::Config;
+use crate::error::Error;
+use crate::plan::Plan;
+use crate::reader::{self, ErrorLog};
+
+/// Number of leading records shown in the picker.
+const SAMPLE: usize = 10;
+
+/// Screen row, counting from 1 as terminals do, on which the first record is drawn.
+const FIRST_ROW: usize = 3;
+
+/// Columns drawn before each record, and between each of its fields.
+const GUTTER: usize = 2;
+
+const HELP: &str = "\u{2190}/\u{2192} move  space/click toggle  v start range  u undo  c clear  \
+                    enter accept  q quit";
+
+/// Switch to the alternate screen, hide the cursor and report mouse clicks.
+const ENTER: &[u8] = b"\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h";
+const LEAVE: &[u8] = b"\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Left,
+    Right,
+    Enter,
+    Esc,
+    Backspace,
+    Char(char),
+    /// A press of the left mouse button, at a 1-based column and row
+    Click {
+        col: usize,
+        row: usize,
+    },
+}
+
+/// How the user ended a session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Accept,
+    Cancel,
+}
+
+/// Decode the keys, arrow key escapes and SGR mouse reports in `bytes`, as read from a terminal in
+/// raw mode. Anything else is ignored.
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (key, len) = match rest {
+            [0x1b, b'[', b'<', ..] => match rest.iter().position(|b| *b == b'M' || *b == b'm') {
+                Some(end) => (parse_click(&rest[3..end], rest[end]), end + 1),
+                None => (None, rest.len()),
+            },
+            [0x1b, b'[', c, ..] | [0x1b, b'O', c, ..] => match c {
+                b'C' => (Some(Key::Right), 3),
+                b'D' => (Some(Key::Left), 3),
+                _ => (None, 3),
+            },
+            [0x1b, ..] => (Some(Key::Esc), 1),
+            [b'\r', ..] | [b'\n', ..] => (Some(Key::Enter), 1),
+            [0x7f, ..] | [0x08, ..] => (Some(Key::Backspace), 1),
+            [b, ..] => {
+                let len = match b {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xff => 4,
+                    _ => 1,
+                };
+                let len = len.min(rest.len());
+                let c = std::str::from_utf8(&rest[..len])
+                    .ok()
+                    .and_then(|s| s.chars().next());
+                (c.map(Key::Char), len)
+            }
+            [] => unreachable!(),
+        };
+        keys.extend(key);
+        rest = &rest[len..];
+    }
+    keys
+}
+
+/// Parse the `button;col;row` parameters of an SGR mouse report, which ends in `M` for a press.
+fn parse_click(params: &[u8], end: u8) -> Option<Key> {
+    let mut params = std::str::from_utf8(params)
+        .ok()?
+        .split(';')
+        .map(|p| p.parse::<usize>().ok());
+    let (button, col, row) = (params.next()??, params.next()??, params.next()??);
+    if button == 0 && end == b'M' {
+        Some(Key::Click { col, row })
+    } else {
+        None
+    }
+}
+
+/// The state of an interactive session: sample records, the field under the cursor, and the
+/// choices picked so far, which are kept in the config so that the live output uses them.
+pub struct Picker {
+    config: Config,
+    records: Vec<String>,
+    fields: usize,
+    cursor: usize,
+    anchor: Option<usize>,
+}
+
+impl Picker {
+    pub fn new(config: Config, records: Vec<String>) -> Self {
+        let records: Vec<String> = records
+            .iter()
+            .map(|r| String::from(config.split_terminator(r).0))
+            .collect();
+        let fields = records
+            .iter()
+            .map(|r| config.split_fields(r).count())
+            .max()
+            .unwrap_or(0);
+        Picker {
+            config,
+            records,
+            fields,
+            cursor: 0,
+            anchor: None,
+        }
+    }
+
+    /// Apply `key`, returning how the session ended if it did.
+    pub fn handle(&mut self, key: Key) -> Option<Outcome> {
+        match key {
+            Key::Left | Key::Char('h') => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right | Key::Char('l') => {
+                self.cursor = (self.cursor + 1).min(self.fields.saturating_sub(1))
+            }
+            Key::Char(' ') => self.toggle(),
+            Key::Char('v') => {
+                self.anchor = match self.anchor {
+                    Some(_) => None,
+                    None => Some(self.cursor),
+                }
+            }
+            Key::Char('u') | Key::Backspace => {
+                self.config.opt.choice.pop();
+            }
+            Key::Char('c') => {
+                self.config.opt.choice.clear();
+                self.anchor = None;
+            }
+            Key::Click { col, row } => {
+                if let Some(field) = self.field_at(col, row) {
+                    self.cursor = field;
+                    self.toggle();
+                }
+            }
+            Key::Enter => return Some(Outcome::Accept),
+            Key::Esc | Key::Char('q') | Key::Char('\u{3}') => return Some(Outcome::Cancel),
+            _ => (),
+        }
+        None
+    }
+
+    /// Add the field under the cursor, or the range from the anchor to it, to the choices, or
+    /// remove it if it was already there.
+    fn toggle(&mut self) {
+        let start = self.anchor.take().unwrap_or(self.cursor);
+        let mut choice = Choice::new(start as isize, self.cursor as isize);
+        if let Some(transforms) = &self.config.opt.transform {
+            choice.transforms = transforms.clone();
+        }
+        let choices = &mut self.config.opt.choice;
+        match choices
+            .iter()
+            .position(|c| c.start == choice.start && c.end == choice.end && !c.has_path())
+        {
+            Some(i) => {
+                choices.remove(i);
+            }
+            None => choices.push(choice),
+        }
+    }
+
+    /// Find the field drawn at the 1-based `col` and `row` of the screen.
+    fn field_at(&self, col: usize, row: usize) -> Option<usize> {
+        let record = self.records.get(row.checked_sub(FIRST_ROW)?)?;
+        let mut start = GUTTER + 1;
+        for (i, field) in self.config.split_fields(record).enumerate() {
+            let end = start + field.width();
+            if (start..end.max(start + 1)).contains(&col) {
+                return Some(i);
+            }
+            start = end + GUTTER;
+        }
+        None
+    }
+
+    /// The `choose` command line that makes the current choices.
+    pub fn command(&self) -> String {
+        let opt = &self.config.opt;
+        let mut args = vec![String::from("choose")];
+        if let Some(separator) = &opt.field_separator {
+            args.push(String::from("-f"));
+            args.push(quote(separator));
+        }
+        if opt.non_greedy {
+            args.push(String::from("-n"));
+        }
+        if opt.exclusive {
+            args.push(String::from("-x"));
+        }
+        if let Some(separator) = &opt.output_field_separator {
+            args.push(String::from("-o"));
+            args.push(quote(separator));
+        }
+        if let Some(path) = &opt.input {
+            args.push(String::from("-i"));
+            args.push(quote(&path.display().to_string()));
+        }
+        args.extend(opt.choice.iter().map(|c| {
+            if opt.exclusive {
+                quote(&c.exclusive().to_string())
+            } else {
+                quote(&c.to_string())
+            }
+        }));
+        args.join(" ")
+    }
+
+    /// Draw the records with the cursor and choices highlighted, the output the choices make from
+    /// them, and the command line that makes it.
+    pub fn render<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "\x1b[H\x1b[2J{}\r\n", HELP)?;
+        match self.anchor {
+            Some(anchor) => write!(out, "range from field {}\r\n", anchor)?,
+            None => write!(out, "\r\n")?,
+        }
+
+        for record in &self.records {
+            let fields: Vec<&str> = self.config.split_fields(record).collect();
+            let mut chosen = vec![false; fields.len()];
+            for choice in &self.config.opt.choice {
+                if let Some((lo, hi, _)) = choice.get_bounds(fields.len()) {
+                    chosen[lo..=hi].iter_mut().for_each(|c| *c = true);
+                }
+            }
+
+            write!(out, "{:1$}", "", GUTTER)?;
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    write!(out, "{:1$}", "", GUTTER)?;
+                }
+                let style = match (chosen[i], i == self.cursor) {
+                    (true, true) => "\x1b[1;4;7m",
+                    (true, false) => "\x1b[1;4m",
+                    (false, true) => "\x1b[7m",
+                    (false, false) => "",
+                };
+                // an empty field under the cursor is drawn as a space so that it can be seen
+                let field = if field.is_empty() && i == self.cursor {
+                    " "
+                } else {
+                    field
+                };
+                write!(out, "{}{}\x1b[0m", style, field)?;
+            }
+            write!(out, "\r\n")?;
+        }
+
+        write!(out, "\r\noutput:\r\n")?;
+        let mut plan = Plan::new(&self.config.opt.choice);
+        for record in &self.records {
+            let mut preview = BufWriter::new(Vec::new());
+            plan.print_record(record.as_bytes(), &self.config, &mut preview)?;
+            let preview = preview.into_inner().map_err(|e| e.into_error())?;
+            let preview = String::from_utf8_lossy(&preview);
+            let preview = preview.trim_end_matches(self.config.opt.terminator as char);
+            write!(out, "{:2$}{}\r\n", "", preview, GUTTER)?;
+        }
+
+        write!(out, "\r\ncommand: {}", self.command())
+    }
+}
+
+/// Quote `arg` for a POSIX shell, unless it is made only of characters that need no quoting.
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        String::from(arg)
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Show the first records of `read` in a picker on the terminal and, once the user accepts, write
+/// the command line that chooses what they picked to `handle`.
+pub fn run<R: Read, W: Write>(
+    read: R,
+    config: Config,
+    handle: &mut W,
+    log: &mut ErrorLog,
+) -> Result<(), Error> {
+    let records = reader::sample(read, SAMPLE, &config, log);
+    let mut picker = Picker::new(config, records);
+    if session(&mut picker).map_err(Error::Terminal)? == Outcome::Accept {
+        writeln!(handle, "{}", picker.command()).map_err(Error::Write)?;
+    }
+    Ok(())
+}
+
+/// Redraw `picker` and feed it keys from the terminal until the user accepts or cancels.
+fn session(picker: &mut Picker) -> io::Result<Outcome> {
+    let mut terminal = Terminal::open()?;
+    let mut buf = [0; 64];
+    loop {
+        let mut screen = Vec::new();
+        picker.render(&mut screen)?;
+        terminal.tty.write_all(&screen)?;
+        terminal.tty.flush()?;
+
+        let n = terminal.tty.read(&mut buf)?;
+        if n == 0 {
+            return Ok(Outcome::Cancel);
+        }
+        for key in parse_keys(&buf[..n]) {
+            if let Some(outcome) = picker.handle(key) {
+                return Ok(outcome);
+            }
+        }
+    }
+}
+
+/// The controlling terminal, in raw mode on the alternate screen until dropped. Keys are read from
+/// it rather than stdin, which may be the input.
+struct Terminal {
+    tty: File,
+    #[cfg(unix)]
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl Terminal {
+    fn open() -> io::Result<Self> {
+        use std::fs::OpenOptions;
+        use std::os::unix::io::AsRawFd;
+
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let fd = tty.as_raw_fd();
+        // SAFETY: `termios` is plain data filled in by `tcgetattr`, and `fd` stays open as long as
+        // `tty` does.
+        let original = unsafe {
+            let mut termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios
+        };
+        let mut raw = original;
+        // SAFETY: as above.
+        unsafe {
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        let mut terminal = Terminal { tty, original };
+        terminal.tty.write_all(ENTER)?;
+        Ok(terminal)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        use std::os::unix::io::AsRawFd;
+
+        let _ = self.tty.write_all(LEAVE);
+        let _ = self.tty.flush();
+        // SAFETY: restores the settings read in `open` on the same, still open, terminal.
+        unsafe {
+            libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Terminal {
+    fn open() -> io::Result<Self> {
+        let _ = (ENTER, LEAVE);
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "--interactive needs a Unix terminal",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker(args: &[&str], input: &str) -> Picker {
+        let mut argv = vec!["choose", "--interactive"];
+        argv.extend_from_slice(args);
+        let config = Config::from_iter(argv);
+        let records = input.split_inclusive('\n').map(String::from).collect();
+        Picker::new(config, records)
+    }
+
+    /// Feed `keys`, as a terminal would send them, to `picker` until the session ends.
+    fn script(picker: &mut Picker, keys: &[u8]) -> Option<Outcome> {
+        parse_keys(keys)
+            .into_iter()
+            .find_map(|key| picker.handle(key))
+    }
+
+    fn screen(picker: &Picker) -> String {
+        let mut out = Vec::new();
+        picker.render(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn parse_arrows_and_chars() {
+        assert_eq!(
+            vec![
+                Key::Right,
+                Key::Left,
+                Key::Right,
+                Key::Char(' '),
+                Key::Char('é'),
+                Key::Enter
+            ],
+            parse_keys(b"\x1b[C\x1b[D\x1bOC \xc3\xa9\r")
+        );
+    }
+
+    #[test]
+    fn parse_lone_escape() {
+        assert_eq!(vec![Key::Esc], parse_keys(b"\x1b"));
+    }
+
+    #[test]
+    fn parse_mouse_press_only() {
+        assert_eq!(
+            vec![Key::Click { col: 12, row: 4 }],
+            parse_keys(b"\x1b[<0;12;4M\x1b[<0;12;4m\x1b[<2;1;1M")
+        );
+    }
+
+    #[test]
+    fn toggle_fields_with_arrows() {
+        let mut picker = picker(&[], "a b c\nd e f\n");
+        assert_eq!(
+            Some(Outcome::Accept),
+            script(&mut picker, b"\x1b[C\x1b[C \x1b[D\x1b[D \r")
+        );
+        assert_eq!("choose 2 0", picker.command());
+    }
+
+    #[test]
+    fn toggle_twice_removes() {
+        let mut picker = picker(&[], "a b c\n");
+        script(&mut picker, b"l  l ");
+        assert_eq!("choose 2", picker.command());
+    }
+
+    #[test]
+    fn pick_range() {
+        let mut picker = picker(&[], "a b c d\n");
+        script(&mut picker, b"lvll ");
+        assert_eq!("choose 1:3", picker.command());
+    }
+
+    #[test]
+    fn cursor_stops_at_last_field() {
+        let mut picker = picker(&[], "a b\nc d e\n");
+        script(&mut picker, b"lllll ");
+        assert_eq!("choose 2", picker.command());
+    }
+
+    #[test]
+    fn click_toggles_field() {
+        let mut picker = picker(&[], "ab cd ef\n");
+        // "  ab  cd  ef" is drawn on row 3, so column 8 is within `cd`
+        script(&mut picker, b"\x1b[<0;8;3M");
+        assert_eq!("choose 1", picker.command());
+        // clicking between fields or off the records does nothing
+        script(&mut picker, b"\x1b[<0;6;3M\x1b[<0;3;9M");
+        assert_eq!("choose 1", picker.command());
+    }
+
+    #[test]
+    fn undo_and_clear() {
+        let mut picker = picker(&["0"], "a b c\n");
+        script(&mut picker, b"l l u");
+        assert_eq!("choose 0 1", picker.command());
+        script(&mut picker, b"u");
+        assert_eq!("choose 0", picker.command());
+        script(&mut picker, b"c");
+        assert_eq!("choose", picker.command());
+    }
+
+    #[test]
+    fn cancel() {
+        let mut picker = picker(&[], "a b c\n");
+        assert_eq!(Some(Outcome::Cancel), script(&mut picker, b" q"));
+        assert_eq!(Some(Outcome::Cancel), script(&mut picker, b"\x1b"));
+    }
+
+    #[test]
+    fn command_keeps_separators_and_quotes() {
+        let mut picker = picker(&["-f", "[ ,]", "-o", "'", "-n", "-1"], "a,b\n");
+        script(&mut picker, b" ");
+        assert_eq!("choose -f '[ ,]' -n -o ''\\''' -1 0", picker.command());
+    }
+
+    #[test]
+    fn picked_fields_get_global_transform() {
+        let mut picker = picker(&["--transform", "upper", "0"], "a b c\n");
+        script(&mut picker, b"l ");
+        let screen = screen(&picker);
+        assert!(screen.contains("output:\r\n  A B\r\n"));
+        assert!(screen.ends_with("command: choose '0|upper' '1|upper'"));
+    }
+
+    #[test]
+    fn command_keeps_exclusive_ranges() {
+        let mut picker = picker(&["-x", ":0", "1:3"], "a b c d\n");
+        script(&mut picker, b"ll ");
+        assert_eq!("choose -x 0:0 1:3 2:3", picker.command());
+    }
+
+    #[test]
+    fn render_shows_live_output() {
+        let mut picker = picker(&[], "a b c\nd e f\n");
+        script(&mut picker, b"lvl ");
+        let screen = screen(&picker);
+        assert!(screen.contains("output:\r\n  b c\r\n  e f\r\n"));
+        assert!(screen.ends_with("command: choose 1:2"));
+    }
+}
@@ -0,0 +1,67 @@
+use serde_json::Value;
+use std::convert::TryInto;
+use std::io;
+
+/// Parse `line` as a JSON value. A record that is not valid JSON is an `InvalidData` error, which
+/// is reported and skipped like a record that is not valid UTF-8.
+pub fn parse_record(line: &str) -> io::Result<Value> {
+    serde_json::from_str(line)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid JSON: {}", e)))
+}
+
+/// Follow `path` down from `value`, treating each segment as an object key or, for arrays, as an
+/// index that may be negative to count from the end.
+pub fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |v, segment| match v {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => {
+            let index: isize = segment.parse().ok()?;
+            let index: usize = if index < 0 {
+                items.len().checked_sub(index.unsigned_abs())?
+            } else {
+                index.try_into().ok()?
+            };
+            items.get(index)
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(p: &[&str]) -> Vec<String> {
+        p.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn lookup_object_key() {
+        let record = parse_record(r#"{"user": {"id": 7}}"#).unwrap();
+        assert_eq!(
+            Some(&Value::from(7)),
+            lookup(&record, &path(&["user", "id"]))
+        );
+    }
+
+    #[test]
+    fn lookup_negative_array_index() {
+        let record = parse_record(r#"{"tags": ["a", "b", "c"]}"#).unwrap();
+        assert_eq!(
+            Some(&Value::from("c")),
+            lookup(&record, &path(&["tags", "-1"]))
+        );
+    }
+
+    #[test]
+    fn lookup_out_of_bounds_index() {
+        let record = parse_record(r#"{"tags": ["a"]}"#).unwrap();
+        assert_eq!(None, lookup(&record, &path(&["tags", "-2"])));
+    }
+
+    #[test]
+    fn lookup_missing_key() {
+        let record = parse_record(r#"{"user": "x"}"#).unwrap();
+        assert_eq!(None, lookup(&record, &path(&["user", "id"])));
+    }
+}
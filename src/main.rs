@@ -1,60 +1,132 @@
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
 use std::process;
 use structopt::StructOpt;
 
-#[macro_use]
-extern crate lazy_static;
-
+mod aggregate;
 mod choice;
 mod config;
+mod debug;
+mod error;
+mod highlight;
+mod histogram;
+mod indices;
+mod interactive;
+mod json;
 mod opt;
+mod parallel;
+mod plan;
 mod reader;
+mod transform;
+mod transpose;
+mod unique;
 mod writeable;
 mod writer;
 use config::Config;
+use error::Error;
 use opt::Opt;
-use writer::WriteReceiver;
+use plan::Plan;
+use reader::ErrorLog;
+use unique::Unique;
 
 fn main() {
+    if let Err(e) = try_main() {
+        if !e.is_quiet() {
+            eprintln!("{}", e);
+        }
+        process::exit(e.exit_code());
+    }
+}
+
+fn try_main() -> Result<(), Error> {
     let opt = Opt::from_args();
-    let config = Config::new(opt);
-
-    let read = match &config.opt.input {
-        Some(f) => match File::open(f) {
-            Ok(fh) => Box::new(fh) as Box<dyn Read>,
-            Err(e) => {
-                eprintln!("Failed to open file: {}", e);
-                // exit code of 3 means failure to open input file
-                process::exit(3);
-            }
-        },
-        None => Box::new(io::stdin()) as Box<dyn Read>,
-    };
+    let config = Config::new(opt, io::stdout().is_terminal())?;
+    debug::print_config(&config);
 
-    let mut reader = reader::BufReader::new(read);
-    let mut buffer = String::new();
+    let file = match &config.opt.input {
+        Some(path) => Some(File::open(path).map_err(|source| Error::Open {
+            path: path.clone(),
+            source,
+        })?),
+        None => None,
+    };
 
     let stdout = io::stdout();
     let lock = stdout.lock();
     let mut handle = io::BufWriter::new(lock);
 
-    while let Some(line) = reader.read_line(&mut buffer) {
-        match line {
-            Ok(l) => {
-                let choice_iter = &mut config.opt.choice.iter().peekable();
-                while let Some(choice) = choice_iter.next() {
-                    choice.print_choice(&l, &config, &mut handle);
-                    if choice_iter.peek().is_some() {
-                        handle.write_separator(&config);
-                    }
-                }
-                match handle.write(b"\n") {
-                    Ok(_) => (),
-                    Err(e) => eprintln!("Failed to write to output: {}", e),
-                }
+    let mut log = ErrorLog::new(&config);
+    if config.opt.interactive {
+        interactive::run(input(file), config, &mut handle, &mut log)?;
+    } else {
+        run(file, &config, &mut handle, &mut log).map_err(Error::Write)?;
+    }
+    handle.flush().map_err(Error::Write)?;
+    log.finish()
+}
+
+fn run<W: Write>(
+    file: Option<File>,
+    config: &Config,
+    handle: &mut BufWriter<W>,
+    log: &mut ErrorLog,
+) -> io::Result<()> {
+    if config.opt.show_indices {
+        return indices::run(input(file), config, handle, log);
+    }
+    if aggregate::requested(config) {
+        return aggregate::run(input(file), config, handle, log);
+    }
+    if histogram::requested(config) {
+        return histogram::run(input(file), config, handle, log);
+    }
+    if config.opt.transpose {
+        return transpose::run(input(file), config, handle, log);
+    }
+
+    // whether a record repeats an earlier one depends on every record before it
+    let parallel = config.opt.threads > 1 && !config.opt.unique;
+    let mut plan = Plan::new(&config.opt.choice);
+    let mut unique = Unique::new(config);
+
+    let map = match &file {
+        Some(fh) if !config.opt.no_mmap => reader::map(fh),
+        _ => None,
+    };
+    if let Some(map) = map {
+        if parallel {
+            return parallel::run_mapped(&map, config, handle, log);
+        }
+
+        for (i, l) in reader::Records::new(&map, config.opt.terminator).enumerate() {
+            debug::print_record(i + 1, l, config);
+            let printed = unique.print_record(&mut plan, l, config, handle);
+            if !reader::skip_rejected(printed, i + 1, log)? {
+                break;
             }
-            Err(e) => println!("Failed to read line: {}", e),
         }
+        unique.finish(config);
+        return Ok(());
+    }
+
+    let read = input(file);
+
+    if parallel {
+        return parallel::run(read, config, handle, log);
+    }
+
+    reader::for_each_bytes(read, config, log, |line, l| {
+        debug::print_record(line, l, config);
+        unique.print_record(&mut plan, l, config, handle)
+    })?;
+    unique.finish(config);
+    Ok(())
+}
+
+/// Read from the input file if one was given, or from stdin.
+fn input(file: Option<File>) -> Box<dyn Read + Send> {
+    match file {
+        Some(fh) => Box::new(fh),
+        None => Box::new(io::stdin()),
     }
 }
@@ -1,21 +1,79 @@
 use std::path::PathBuf;
+use structopt::clap::ArgGroup;
 use structopt::StructOpt;
 
 use crate::choice::Choice;
-use crate::config::Config;
+use crate::config::{
+    ColorWhen, Config, Label, ReadErrorPolicy, SortBy, Straddle, TransposeMode, UniqueMode,
+};
+use crate::transform::Pipeline;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "choose", about = "`choose` sections from each line of files")]
 #[structopt(setting = structopt::clap::AppSettings::AllowLeadingHyphen)]
+#[structopt(group = ArgGroup::with_name("aggregate")
+    .multiple(true)
+    .conflicts_with_all(&["bytes", "cols", "highlight", "interactive", "show-indices"]))]
+#[structopt(group = ArgGroup::with_name("frequency")
+    .multiple(true)
+    .conflicts_with_all(&["aggregate", "bytes", "cols", "group-by", "highlight", "interactive",
+        "show-indices"]))]
 pub struct Opt {
+    /// With --histogram or --top, draw a bar after each count, scaled so that the largest fills the
+    /// width of the terminal
+    #[structopt(long, requires = "frequency")]
+    pub bars: bool,
+
+    /// Choose fields by byte offset, reading records that need not be valid UTF-8
+    #[structopt(
+        short,
+        long,
+        conflicts_with_all = &["character-wise", "graphemes", "json"]
+    )]
+    pub bytes: bool,
+
     /// Choose fields by character number
     #[structopt(short, long)]
     pub character_wise: bool,
 
-    /// Activate debug mode
+    /// Color the output of each choice, or each labelled field of --show-indices, differently,
+    /// leaving separators uncolored. `auto` colors output to a terminal unless NO_COLOR is set.
+    /// JSON output is never colored
+    #[structopt(
+        long,
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"]
+    )]
+    pub color: ColorWhen,
+
+    /// Print how many items each choice selects across all records, or how many records there are
+    /// if there are no choices, once at the end of input instead of printing each record.
+    /// Aggregates are printed in the order count, sum, mean, min, max, distinct
+    #[structopt(long, group = "aggregate")]
+    pub count: bool,
+
+    /// Choose by terminal display column, counting East Asian wide characters and emoji as two
+    /// columns
+    #[structopt(
+        long,
+        conflicts_with_all = &["bytes", "character-wise", "graphemes", "json"]
+    )]
+    pub cols: bool,
+
+    /// With --threads, the most chunks of input held in memory at once [default: twice the number
+    /// of threads]
+    #[structopt(long)]
+    pub chunks_in_flight: Option<usize>,
+
+    /// Print to stderr how the choices and separator were understood, and how the first few
+    /// records are split
     #[structopt(short, long)]
     pub debug: bool,
 
+    /// Print how many distinct items each choice selects across all records
+    #[structopt(long, group = "aggregate")]
+    pub distinct: bool,
+
     /// Use exclusive ranges, similar to array indexing in many programming languages
     #[structopt(short = "x", long)]
     pub exclusive: bool,
@@ -24,21 +82,218 @@ pub struct Opt {
     #[structopt(short, long)]
     pub field_separator: Option<String>,
 
+    /// Summarize each group of records that share a key instead of the whole input, printing the
+    /// key before the group's aggregates. May be given more than once to key by several choices,
+    /// e.g. `--group-by 0 --group-by 2 --count`
+    #[structopt(
+        long,
+        number_of_values = 1,
+        requires = "aggregate",
+        parse(try_from_str = Config::parse_choice)
+    )]
+    pub group_by: Vec<Choice>,
+
+    /// Choose fields by extended grapheme cluster, keeping combining marks, emoji sequences and
+    /// flags intact
+    #[structopt(short, long, conflicts_with_all = &["character-wise", "json"])]
+    pub graphemes: bool,
+
+    /// Print each whole record with the parts that the choices select marked, by default in bold
+    /// and underlined or, when output is not colored, between [[ and ]]
+    #[structopt(
+        long,
+        conflicts_with_all = &["bytes", "cols", "interactive", "json", "show-indices"]
+    )]
+    pub highlight: bool,
+
+    /// Print each distinct combination of what the choices select with the number of records it
+    /// is found in, from the most common to the least, once at the end of input
+    #[structopt(long, group = "frequency")]
+    pub histogram: bool,
+
     /// Input file
     #[structopt(short, long, parse(from_os_str))]
     pub input: Option<PathBuf>,
 
+    /// Pick fields from the first few records in a terminal UI, then print the command line that
+    /// chooses them
+    #[structopt(
+        long,
+        conflicts_with_all = &["bytes", "character-wise", "cols", "graphemes", "json",
+            "show-indices"]
+    )]
+    pub interactive: bool,
+
+    /// Parse each line as a JSON value and choose by dotted paths, e.g. `user.id`, `tags.-1` or
+    /// `items.0:2`
+    #[structopt(long, conflicts_with = "character-wise")]
+    pub json: bool,
+
+    /// With --transpose, start each row with the index of its items in their records, or with the
+    /// item at the same place in the first record, which is then taken as a header
+    #[structopt(long, requires = "transpose", possible_values = &["index", "header"])]
+    pub label: Option<Label>,
+
+    /// Parse numbers for --sum, --mean, --min and --max leniently, ignoring thousands separators
+    /// (`,` and `_`) and any currency symbol or unit around them, as in `$1,200` or `35ms`
+    #[structopt(long, requires = "aggregate")]
+    pub lenient_numbers: bool,
+
+    /// With --highlight, the text to put around the selected parts of a record, as the opening
+    /// and closing markers either side of `...`, e.g. `<<...>>`
+    #[structopt(long, requires = "highlight", parse(try_from_str = Config::parse_markers))]
+    pub markers: Option<(String, String)>,
+
+    /// Print the largest number each choice selects across all records
+    #[structopt(long, group = "aggregate")]
+    pub max: bool,
+
+    /// Print the mean of the numbers each choice selects across all records
+    #[structopt(long, group = "aggregate")]
+    pub mean: bool,
+
+    /// Print the smallest number each choice selects across all records
+    #[structopt(long, group = "aggregate")]
+    pub min: bool,
+
+    /// Read the input file through a buffer instead of mapping it into memory
+    #[structopt(long)]
+    pub no_mmap: bool,
+
     /// Use non-greedy field separators
     #[structopt(short, long)]
     pub non_greedy: bool,
 
-    /// Specify output field separator
+    /// What to do with a record that cannot be read, such as one that is not valid UTF-8: report it
+    /// and skip to the next, or report it and stop. Either way the exit status is 5
+    #[structopt(
+        long,
+        default_value = "skip",
+        possible_values = &["skip", "abort"]
+    )]
+    pub on_read_error: ReadErrorPolicy,
+
+    /// Separator written between the output of each choice [default: the output field separator]
+    #[structopt(long)]
+    pub outer_separator: Option<String>,
+
+    /// Specify output field separator, written between the fields of a range. A choice may
+    /// override it for itself by ending in `/` and the separator, e.g. `0:2/,`
     #[structopt(short, long, parse(from_str = Config::parse_output_field_separator))]
     pub output_field_separator: Option<String>,
 
+    /// Write each record's original terminator, such as `\r\n` or none for a final line without
+    /// one, instead of the output terminator
+    #[structopt(long)]
+    pub preserve_terminators: bool,
+
+    /// With --group-by, the input is already sorted by key: print each group as soon as the key
+    /// changes, in input order, rather than holding every group until the end
+    #[structopt(long, requires = "group-by")]
+    pub presorted: bool,
+
+    /// Print the first few records with each field labelled by its positive and negative index,
+    /// instead of choosing from them
+    #[structopt(
+        long,
+        conflicts_with_all = &["bytes", "character-wise", "cols", "graphemes", "json"]
+    )]
+    pub show_indices: bool,
+
+    /// With --group-by, print groups in ascending order of key, or from the most records to the
+    /// fewest
+    #[structopt(
+        long,
+        default_value = "key",
+        possible_values = &["key", "count"]
+    )]
+    pub sort_by: SortBy,
+
+    /// With --cols, what to do with a wide character straddling the edge of a range: drop it, or
+    /// pad the columns it covers inside the range with spaces
+    #[structopt(
+        long,
+        default_value = "drop",
+        possible_values = &["drop", "pad"]
+    )]
+    pub straddle: Straddle,
+
+    /// Print the sum of the numbers each choice selects across all records. Items that are not
+    /// numbers are reported and left out of this and the other numeric aggregates
+    #[structopt(long, group = "aggregate")]
+    pub sum: bool,
+
+    /// Character ending each input and output record. Accepts a single ASCII character or one of
+    /// the escapes \0, \n, \r or \t
+    #[structopt(long, default_value = "\\n", parse(try_from_str = Config::parse_terminator))]
+    pub terminator: u8,
+
+    /// Number of threads to process input with, splitting it into chunks of whole records and
+    /// writing results in input order
+    #[structopt(long, default_value = "1")]
+    pub threads: usize,
+
+    /// Like --histogram, printing only the N most common combinations
+    #[structopt(long, value_name = "N", group = "frequency")]
+    pub top: Option<usize>,
+
+    /// Transforms applied to every chosen item before those of its own choice, separated by `|`:
+    /// upper, lower, trim, trim-chars:C, truncate:N, pad:N, replace:/re/repl/, basename, dirname
+    #[structopt(long, parse(try_from_str = Pipeline::parse))]
+    pub transform: Option<Pipeline>,
+
+    /// Print each item chosen from a record on a row of its own, or with --transpose-mode table,
+    /// each column of the items chosen from all records as a row, its items separated by the
+    /// output field separator
+    #[structopt(
+        long,
+        conflicts_with_all = &["aggregate", "bytes", "cols", "frequency", "highlight",
+            "interactive", "json", "show-indices", "unique"]
+    )]
+    pub transpose: bool,
+
+    /// With --transpose, turn the items of each record into rows, with a blank line between
+    /// records, or the columns of the whole input, which is held in memory until its end
+    #[structopt(
+        long,
+        default_value = "record",
+        possible_values = &["record", "table"]
+    )]
+    pub transpose_mode: TransposeMode,
+
+    /// Leave out output records that repeat an earlier one, comparing what is chosen rather than
+    /// whole input records. With -d, the number left out is reported. Records are then processed
+    /// on one thread
+    #[structopt(
+        long,
+        conflicts_with_all = &["aggregate", "frequency", "interactive", "show-indices"]
+    )]
+    pub unique: bool,
+
+    /// With --unique, compare each output record with only the one before it, in constant memory,
+    /// with every one before it, or with every one before it in 16 MiB, at the cost of rarely
+    /// leaving out a record that is not a repeat
+    #[structopt(
+        long,
+        default_value = "global",
+        possible_values = &["adjacent", "global", "approx"]
+    )]
+    pub unique_mode: UniqueMode,
+
+    /// With --bytes, shrink ranges so they never split a UTF-8 encoded character
+    #[structopt(long, requires = "bytes")]
+    pub utf8_boundaries: bool,
+
     /// Fields to print. Either x, x:, :y, or x:y, where x and y are integers, colons indicate a
     /// range, and an empty field on either side of the colon continues to the beginning or end of
-    /// the line.
-    #[structopt(required = true, min_values = 1, parse(try_from_str = Config::parse_choice))]
+    /// the line. With --json, a dotted path may precede the last segment, which is either a key, an
+    /// index, or a range; a key at the top level takes a leading `.`, as in `.name`. A trailing `/`
+    /// and text sets the separator between that choice's fields, and transforms may follow, each
+    /// after a `|`, e.g. `2|trim|upper|truncate:8`.
+    #[structopt(
+        required_unless_one = &["count", "interactive", "show-indices"],
+        min_values = 1,
+        parse(try_from_str = Config::parse_choice)
+    )]
     pub choice: Vec<Choice>,
 }
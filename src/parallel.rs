@@ -0,0 +1,290 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::config::{Config, ReadErrorPolicy};
+use crate::debug;
+use crate::plan::Plan;
+use crate::reader::{ErrorLog, Records};
+
+/// Approximate size of each chunk handed to a worker; chunks are extended to the next terminator
+/// so that no record is split between two of them.
+const CHUNK_SIZE: u64 = 1 << 20;
+
+/// A chunk of input, numbered in input order and tagged with the line its first record is on. A
+/// chunk of a mapped file is borrowed from the mapping rather than copied.
+type Job<'a> = (usize, usize, Cow<'a, [u8]>);
+
+/// A chunk's output, along with the line numbers of the records in it that could not be read.
+struct Processed {
+    output: Vec<u8>,
+    errors: Vec<(usize, io::Error)>,
+}
+
+/// Process `read` in record-aligned chunks spread over `config.opt.threads` workers, writing the
+/// results to `handle` in input order. At most `in_flight` chunks are held in memory at once.
+/// Processing stops at the first error writing output, which is returned, or when `log` says to
+/// stop after a record could not be read.
+pub fn run<R, W>(
+    read: R,
+    config: &Config,
+    handle: &mut BufWriter<W>,
+    log: &mut ErrorLog,
+) -> io::Result<()>
+where
+    R: Read + Send,
+    W: Write,
+{
+    let mut reader = io::BufReader::new(read);
+    let next_chunk = move || {
+        let mut chunk = Vec::new();
+        match read_chunk(&mut reader, config.opt.terminator, &mut chunk)? {
+            0 => Ok(None),
+            _ => Ok(Some(Cow::Owned(chunk))),
+        }
+    };
+    run_chunks(next_chunk, config, handle, log)
+}
+
+/// Process `bytes`, such as a mapped file, like `run`, handing workers slices of it rather than
+/// copies.
+pub fn run_mapped<W: Write>(
+    mut bytes: &[u8],
+    config: &Config,
+    handle: &mut BufWriter<W>,
+    log: &mut ErrorLog,
+) -> io::Result<()> {
+    let next_chunk = move || Ok(split_chunk(&mut bytes, config.opt.terminator).map(Cow::Borrowed));
+    run_chunks(next_chunk, config, handle, log)
+}
+
+/// Process the chunks returned by `next_chunk` until it returns `None`, as `run` describes.
+fn run_chunks<'a, C, W>(
+    mut next_chunk: C,
+    config: &Config,
+    handle: &mut BufWriter<W>,
+    log: &mut ErrorLog,
+) -> io::Result<()>
+where
+    C: FnMut() -> io::Result<Option<Cow<'a, [u8]>>> + Send,
+    W: Write,
+{
+    let in_flight = config
+        .opt
+        .chunks_in_flight
+        .unwrap_or(2 * config.opt.threads);
+    let (token_tx, token_rx) = mpsc::sync_channel(in_flight.max(1) - 1);
+    let (job_tx, job_rx) = mpsc::channel::<Job>();
+    let (result_tx, result_rx) = mpsc::channel();
+    let job_rx = Arc::new(Mutex::new(job_rx));
+
+    thread::scope(|s| {
+        let reader = s.spawn(move || {
+            let mut seq = 0;
+            let mut line = 1;
+            loop {
+                let chunk = match next_chunk() {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => return Ok(()),
+                    Err(e) => return Err((line, e)),
+                };
+                let first_line = line;
+                line += memchr::memchr_iter(config.opt.terminator, &chunk).count();
+                if job_tx.send((seq, first_line, chunk)).is_err() || token_tx.send(()).is_err() {
+                    return Ok(());
+                }
+                seq += 1;
+            }
+        });
+
+        for _ in 0..config.opt.threads {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            s.spawn(move || {
+                let mut plan = Plan::new(&config.opt.choice);
+                while let Some((seq, first_line, chunk)) = next_job(&job_rx) {
+                    let output = process_chunk(&chunk, first_line, config, &mut plan);
+                    if result_tx.send((seq, output)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(result_tx);
+
+        let written = write_in_order(result_rx, token_rx, handle, log);
+        if let Ok(Err((line, e))) = reader.join() {
+            log.report(line, e);
+        }
+        written
+    })
+}
+
+/// Write each chunk's output as soon as those of all earlier chunks have been written, returning
+/// a token for each so that another chunk can be read. Dropping the channels on an error, or when
+/// `log` says to stop, makes the reader and workers stop.
+fn write_in_order<W: Write>(
+    result_rx: Receiver<(usize, io::Result<Processed>)>,
+    token_rx: Receiver<()>,
+    handle: &mut BufWriter<W>,
+    log: &mut ErrorLog,
+) -> io::Result<()> {
+    let mut pending = BTreeMap::new();
+    let mut next = 0;
+    for (seq, output) in result_rx {
+        pending.insert(seq, output);
+        while let Some(processed) = pending.remove(&next) {
+            let processed = processed?;
+            handle.write_all(&processed.output)?;
+            for (line, e) in processed.errors {
+                if !log.report(line, e) {
+                    return Ok(());
+                }
+            }
+            let _ = token_rx.recv();
+            next += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Read roughly `CHUNK_SIZE` bytes into `chunk`, continuing to the end of the record in progress.
+fn read_chunk<R: BufRead>(
+    reader: &mut R,
+    terminator: u8,
+    chunk: &mut Vec<u8>,
+) -> io::Result<usize> {
+    reader.take(CHUNK_SIZE).read_to_end(chunk)?;
+    if chunk.last().is_some_and(|b| *b != terminator) {
+        reader.read_until(terminator, chunk)?;
+    }
+    Ok(chunk.len())
+}
+
+/// Split roughly `CHUNK_SIZE` bytes off the front of `bytes`, continuing to the end of the record
+/// in progress.
+fn split_chunk<'a>(bytes: &mut &'a [u8], terminator: u8) -> Option<&'a [u8]> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let mut end = bytes.len().min(CHUNK_SIZE as usize);
+    if bytes[end - 1] != terminator {
+        end = match memchr::memchr(terminator, &bytes[end..]) {
+            Some(i) => end + i + 1,
+            None => bytes.len(),
+        };
+    }
+    let (chunk, rest) = bytes.split_at(end);
+    *bytes = rest;
+    Some(chunk)
+}
+
+fn next_job<'a>(job_rx: &Mutex<Receiver<Job<'a>>>) -> Option<Job<'a>> {
+    job_rx.lock().ok()?.recv().ok()
+}
+
+/// Process the records of `chunk`, the first of which is on `first_line` of the input. With
+/// `--on-read-error abort`, stops at the first record that cannot be read or is rejected.
+fn process_chunk(
+    chunk: &[u8],
+    first_line: usize,
+    config: &Config,
+    plan: &mut Plan,
+) -> io::Result<Processed> {
+    let mut handle = BufWriter::new(Vec::with_capacity(chunk.len()));
+    let mut errors = Vec::new();
+    for (i, l) in Records::new(chunk, config.opt.terminator).enumerate() {
+        debug::print_record(first_line + i, l, config);
+        match plan.print_record(l, config, &mut handle) {
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                errors.push((first_line + i, e));
+                if config.opt.on_read_error == ReadErrorPolicy::Abort {
+                    break;
+                }
+            }
+            result => result?,
+        }
+    }
+    let output = handle.into_inner().map_err(|e| e.into_error())?;
+    Ok(Processed { output, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_chunk_ends_on_terminator() {
+        let input = "a bc\n".repeat(CHUNK_SIZE as usize / 4);
+        let mut reader = io::BufReader::new(input.as_bytes());
+        let mut chunk = Vec::new();
+        read_chunk(&mut reader, b'\n', &mut chunk).unwrap();
+        assert!(chunk.len() > CHUNK_SIZE as usize);
+        assert_eq!(Some(&b'\n'), chunk.last());
+    }
+
+    #[test]
+    fn split_chunk_ends_on_terminator() {
+        let input = "a bc\n".repeat(CHUNK_SIZE as usize / 4);
+        let mut bytes = input.as_bytes();
+        let chunk = split_chunk(&mut bytes, b'\n').unwrap();
+        assert!(chunk.len() > CHUNK_SIZE as usize);
+        assert_eq!(Some(&b'\n'), chunk.last());
+        assert_eq!(input.len(), chunk.len() + bytes.len());
+        assert_eq!(Some(bytes), split_chunk(&mut bytes, b'\n'));
+        assert_eq!(None, split_chunk(&mut bytes, b'\n'));
+    }
+
+    #[test]
+    fn run_mapped_preserves_input_order() {
+        let config = Config::from_iter(vec!["choose", "1", "--threads", "4"]);
+        let input: String = (0..200_000).map(|i| format!("x {}\n", i)).collect();
+        let expected: String = (0..200_000).map(|i| format!("{}\n", i)).collect();
+        let mut handle = BufWriter::new(Vec::new());
+        let mut log = ErrorLog::new(&config);
+        run_mapped(input.as_bytes(), &config, &mut handle, &mut log).unwrap();
+        assert_eq!(expected.into_bytes(), handle.into_inner().unwrap());
+        assert!(log.finish().is_ok());
+    }
+
+    #[test]
+    fn run_preserves_input_order() {
+        let config = Config::from_iter(vec![
+            "choose",
+            "1",
+            "--threads",
+            "4",
+            "--chunks-in-flight",
+            "3",
+        ]);
+        let input: String = (0..200_000).map(|i| format!("x {}\n", i)).collect();
+        let expected: String = (0..200_000).map(|i| format!("{}\n", i)).collect();
+        let mut handle = BufWriter::new(Vec::new());
+        let mut log = ErrorLog::new(&config);
+        run(input.as_bytes(), &config, &mut handle, &mut log).unwrap();
+        assert_eq!(expected.into_bytes(), handle.into_inner().unwrap());
+        assert!(log.finish().is_ok());
+    }
+
+    #[test]
+    fn process_chunk_numbers_unreadable_records() {
+        let config = Config::from_iter(vec!["choose", "0"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let processed = process_chunk(b"a\n\xff\nb\n\xfe\n", 10, &config, &mut plan).unwrap();
+        assert_eq!(b"a\nb\n".to_vec(), processed.output);
+        let lines: Vec<usize> = processed.errors.iter().map(|(line, _)| *line).collect();
+        assert_eq!(vec![11, 13], lines);
+    }
+
+    #[test]
+    fn process_chunk_aborts_on_unreadable_record() {
+        let config = Config::from_iter(vec!["choose", "0", "--on-read-error", "abort"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let processed = process_chunk(b"a\n\xff\nb\n", 1, &config, &mut plan).unwrap();
+        assert_eq!(b"a\n".to_vec(), processed.output);
+        assert_eq!(1, processed.errors.len());
+    }
+}
@@ -0,0 +1,293 @@
+use std::convert::TryInto;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::str;
+
+use crate::choice::Choice;
+use crate::config::Config;
+use crate::highlight;
+use crate::json;
+use crate::writer::WriteReceiver;
+
+/// All of the choices for a run, compiled so that each record is split into fields at most once for
+/// the choices without a negative index. A choice with a negative index streams the fields of the
+/// record itself, holding only as many as its index can reach back.
+pub struct Plan {
+    /// Number of leading fields that any choice without a negative index can reach, or `None` if
+    /// that is unbounded.
+    limit: Option<usize>,
+    spans: Vec<Range<usize>>,
+}
+
+impl Plan {
+    pub fn new(choices: &[Choice]) -> Self {
+        let limit = choices.iter().try_fold(0, |limit, choice| {
+            if choice.has_negative_index() {
+                return Some(limit);
+            }
+            let last: usize = choice.start.max(choice.end).try_into().ok()?;
+            Some(limit.max(last.checked_add(1)?))
+        });
+
+        Plan {
+            limit,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Print every choice for `line`, separated by the output separator and followed by the record
+    /// terminator.
+    pub fn print_record<W: Write>(
+        &mut self,
+        line: &[u8],
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        self.print_choices(line, config, handle)?;
+        Plan::print_terminator(line, config, handle)
+    }
+
+    /// Print every choice for `line`, separated by the output separator, without a terminator.
+    /// Except with --bytes, a record that is not valid UTF-8, or with --json a record that fails to
+    /// parse, is an `InvalidData` error, returned before anything for it is written.
+    pub fn print_choices<W: Write>(
+        &mut self,
+        line: &[u8],
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        if config.opt.bytes {
+            return Plan::print_bytes(config.split_terminator_bytes(line).0, config, handle);
+        }
+
+        let line =
+            str::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if config.opt.json {
+            let record = json::parse_record(config.split_terminator(line).0)?;
+
+            let choice_iter = &mut config.opt.choice.iter().peekable();
+            while let Some(choice) = choice_iter.next() {
+                choice.print_choice_json(&record, config, handle)?;
+                if choice_iter.peek().is_some() {
+                    handle.write_separator(&config.outer_separator)?;
+                }
+            }
+        } else if config.opt.highlight {
+            highlight::print_record(config.split_terminator(line).0, config, handle)?;
+        } else {
+            self.print_fields(line, config, handle)?;
+        }
+        Ok(())
+    }
+
+    /// Print the terminator of the output record for `line`.
+    pub fn print_terminator<W: Write>(
+        line: &[u8],
+        config: &Config,
+        handle: &mut W,
+    ) -> io::Result<()> {
+        let terminator = if config.opt.preserve_terminators {
+            config.split_terminator_bytes(line).1
+        } else {
+            std::slice::from_ref(&config.opt.terminator)
+        };
+        handle.write_all(terminator)
+    }
+
+    /// Print every choice for `record`, which has no terminator and need not be valid UTF-8.
+    fn print_bytes<W: Write>(
+        record: &[u8],
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        let choice_iter = &mut config.opt.choice.iter().peekable();
+        while let Some(choice) = choice_iter.next() {
+            choice.print_choice_bytes(record, config, handle)?;
+            if choice_iter.peek().is_some() {
+                handle.write_separator(&config.outer_separator)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_fields<W: Write>(
+        &mut self,
+        line: &str,
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        let split = !(config.opt.cols || config.opt.character_wise || config.opt.graphemes);
+        let record = config.split_terminator(line).0;
+        if split {
+            self.split(record, config);
+        }
+
+        let choice_iter = &mut config.opt.choice.iter().peekable();
+        while let Some(choice) = choice_iter.next() {
+            if split && choice.has_negative_index() {
+                choice.print_choice_generic(config.split_fields(record), config, handle)?;
+            } else if split {
+                let fields = self.spans.iter().map(|span| &line[span.clone()]);
+                choice.print_choice_generic(fields, config, handle)?;
+            } else {
+                choice.print_choice(line, config, handle)?;
+            }
+            if choice_iter.peek().is_some() {
+                handle.write_separator(&config.outer_separator)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the byte span of each field in `line`, stopping once every choice can be resolved.
+    fn split(&mut self, line: &str, config: &Config) {
+        self.spans.clear();
+        let fields = config
+            .split_fields(line)
+            .take(self.limit.unwrap_or(usize::MAX));
+        for field in fields {
+            let start = field.as_ptr() as usize - line.as_ptr() as usize;
+            self.spans.push(start..start + field.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_is_largest_positive_index() {
+        let config = Config::from_iter(vec!["choose", "0", "5:3", "2"]);
+        assert_eq!(Some(6), Plan::new(&config.opt.choice).limit);
+    }
+
+    #[test]
+    fn limit_ignores_negative_index() {
+        let config = Config::from_iter(vec!["choose", "0", "3", "-1", "1:-2"]);
+        assert_eq!(Some(4), Plan::new(&config.opt.choice).limit);
+    }
+
+    #[test]
+    fn split_stops_at_limit() {
+        let config = Config::from_iter(vec!["choose", "0", "2"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        plan.split("a b c d e", &config);
+        assert_eq!(vec![0..1, 2..3, 4..5], plan.spans);
+    }
+
+    #[test]
+    fn split_skips_empty_fields() {
+        let config = Config::from_iter(vec!["choose", "0:", "-f", ":"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        plan.split("a::bc:d", &config);
+        assert_eq!(vec![0..1, 3..5, 6..7], plan.spans);
+    }
+
+    #[test]
+    fn print_record_matches_each_choice() {
+        let config = Config::from_iter(vec!["choose", "3", "0:1", "-1", "-o", ","]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut handle = BufWriter::new(Vec::new());
+        plan.print_record(b"a b c d e\n", &config, &mut handle)
+            .unwrap();
+        assert_eq!(b"d,a,b,e\n".to_vec(), handle.into_inner().unwrap());
+    }
+
+    #[test]
+    fn print_record_streams_negative_choices() {
+        let config = Config::from_iter(vec!["choose", "1", "-2:-1", "1:-3", "-1:2", "-o", ","]);
+        let mut plan = Plan::new(&config.opt.choice);
+        assert_eq!(Some(2), plan.limit);
+        let mut handle = BufWriter::new(Vec::new());
+        plan.print_record(b"a b c d e\n", &config, &mut handle)
+            .unwrap();
+        assert_eq!(plan.spans.len(), 2);
+        assert_eq!(b"b,d,e,b,c,e,d,c\n".to_vec(), handle.into_inner().unwrap());
+    }
+
+    #[test]
+    fn print_record_separates_choices_with_outer_separator() {
+        let config = Config::from_iter(vec![
+            "choose",
+            "0:2",
+            "5:7/-",
+            "-o",
+            ",",
+            "--outer-separator",
+            " | ",
+        ]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut handle = BufWriter::new(Vec::new());
+        plan.print_record(b"a b c d e f g h\n", &config, &mut handle)
+            .unwrap();
+        assert_eq!(b"a,b,c | f-g-h\n".to_vec(), handle.into_inner().unwrap());
+    }
+
+    #[test]
+    fn outer_separator_defaults_to_output_separator() {
+        let config = Config::from_iter(vec!["choose", "0", "1", "-o", ","]);
+        assert_eq!(&b","[..], &config.outer_separator[..]);
+    }
+
+    #[test]
+    fn print_record_skips_terminator_for_bad_json() {
+        let config = Config::from_iter(vec!["choose", "--json", ".a"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut handle = BufWriter::new(Vec::new());
+        for line in &["{\"a\":1}\n", "not json\n", "{\"a\":2}\n"] {
+            let _ = plan.print_record(line.as_bytes(), &config, &mut handle);
+        }
+        assert_eq!(b"1\n2\n".to_vec(), handle.into_inner().unwrap());
+    }
+
+    #[test]
+    fn print_record_rejects_bad_json() {
+        let config = Config::from_iter(vec!["choose", "--json", ".a"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut handle = BufWriter::new(Vec::new());
+        let result = plan.print_record(b"not json\n", &config, &mut handle);
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn print_record_rejects_invalid_utf8() {
+        let config = Config::from_iter(vec!["choose", "0"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut handle = BufWriter::new(Vec::new());
+        let result = plan.print_record(b"\xff x\n", &config, &mut handle);
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+        assert!(handle.into_inner().unwrap().is_empty());
+    }
+
+    #[test]
+    fn print_record_bytes_of_invalid_utf8() {
+        let config = Config::from_iter(vec!["choose", "-b", "2:3", "0"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut handle = BufWriter::new(Vec::new());
+        plan.print_record(b"\xff\xfeab\n", &config, &mut handle)
+            .unwrap();
+        assert_eq!(b"ab\xff\n".to_vec(), handle.into_inner().unwrap());
+    }
+
+    struct BrokenPipe;
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn print_record_returns_write_error() {
+        let config = Config::from_iter(vec!["choose", "0"]);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut handle = BufWriter::with_capacity(0, BrokenPipe);
+        let result = plan.print_record(b"a b\n", &config, &mut handle);
+        assert_eq!(io::ErrorKind::BrokenPipe, result.unwrap_err().kind());
+    }
+}
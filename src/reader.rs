@@ -1,25 +1,295 @@
+use memmap2::Mmap;
+use std::fmt::Display;
+use std::fs::File;
 use std::io::{self, prelude::*};
+use std::str;
+
+use crate::config::{Config, ReadErrorPolicy};
+use crate::error::Error;
 
 pub struct BufReader<R> {
     reader: io::BufReader<R>,
+    terminator: u8,
 }
 
 impl<R: Read> BufReader<R> {
-    pub fn new(f: R) -> Self {
+    pub fn new(f: R, terminator: u8) -> Self {
         Self {
             reader: io::BufReader::new(f),
+            terminator,
         }
     }
 
+    /// Read the next record, up to and including its terminator, into `buffer`.
     pub fn read_line<'buf>(
         &mut self,
         buffer: &'buf mut String,
     ) -> Option<io::Result<&'buf mut String>> {
+        let mut bytes = std::mem::take(buffer).into_bytes();
+        bytes.clear();
+
+        let read = match self.reader.read_until(self.terminator, &mut bytes) {
+            Ok(0) => return None,
+            Ok(_) => bytes,
+            Err(e) => return Some(Err(e)),
+        };
+
+        match String::from_utf8(read) {
+            Ok(s) => {
+                *buffer = s;
+                Some(Ok(buffer))
+            }
+            Err(e) => Some(Err(io::Error::new(io::ErrorKind::InvalidData, e))),
+        }
+    }
+}
+
+/// Read up to `count` records from `read`, skipping any reported to `log` as unreadable.
+pub fn sample<R: Read>(read: R, count: usize, config: &Config, log: &mut ErrorLog) -> Vec<String> {
+    let mut reader = BufReader::new(read, config.opt.terminator);
+    let mut buffer = String::new();
+    let mut records = Vec::new();
+
+    for line in 1..=count {
+        match reader.read_line(&mut buffer) {
+            Some(Ok(l)) => records.push(l.clone()),
+            Some(Err(e)) => {
+                let skippable = e.kind() == io::ErrorKind::InvalidData;
+                if !log.report(line, e) || !skippable {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    records
+}
+
+/// Call `f` with each record of `read`, along with its line number, skipping any reported to `log`
+/// as unreadable: those that are not valid UTF-8, and those that `f` rejects with an `InvalidData`
+/// error, such as invalid JSON.
+pub fn for_each<R, F>(read: R, config: &Config, log: &mut ErrorLog, mut f: F) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(usize, &str) -> io::Result<()>,
+{
+    for_each_bytes(read, config, log, |line, record| {
+        match str::from_utf8(record) {
+            Ok(l) => f(line, l),
+            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    })
+}
+
+/// Call `f` with each record of `read` as bytes, which need not be valid UTF-8, skipping any that
+/// `f` rejects with an `InvalidData` error once they are reported to `log`.
+pub fn for_each_bytes<R, F>(
+    read: R,
+    config: &Config,
+    log: &mut ErrorLog,
+    mut f: F,
+) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(usize, &[u8]) -> io::Result<()>,
+{
+    let mut reader = io::BufReader::new(read);
+    let mut buffer = Vec::new();
+    let mut line = 0;
+
+    loop {
         buffer.clear();
+        line += 1;
+        match reader.read_until(config.opt.terminator, &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(e) => {
+                log.report(line, e);
+                break;
+            }
+        }
+        if !skip_rejected(f(line, &buffer), line, log)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Report `result` to `log` if it rejected the record on `line` as invalid, returning whether to go
+/// on to the next record. Any other error, such as one writing output, is returned.
+pub fn skip_rejected(result: io::Result<()>, line: usize, log: &mut ErrorLog) -> io::Result<bool> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Ok(log.report(line, e)),
+        result => result.map(|()| true),
+    }
+}
+
+/// Map `file` into memory if it is a regular, non-empty file. Pipes, FIFOs and other special files
+/// return `None` and are read through a `BufReader` instead.
+pub fn map(file: &File) -> Option<Mmap> {
+    let metadata = file.metadata().ok()?;
+    if !metadata.is_file() || metadata.len() == 0 {
+        return None;
+    }
+    // SAFETY: the mapping is only read, and it is up to the user not to modify the file while
+    // `choose` reads it, as with any other reader of a file they are writing to.
+    unsafe { Mmap::map(file) }.ok()
+}
+
+/// Iterator over the records of an in-memory input, each borrowed along with its terminator and
+/// left for the caller to check as UTF-8 if it needs to.
+pub struct Records<'a> {
+    bytes: &'a [u8],
+    terminator: u8,
+}
+
+impl<'a> Records<'a> {
+    pub fn new(bytes: &'a [u8], terminator: u8) -> Self {
+        Records { bytes, terminator }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let end = match memchr::memchr(self.terminator, self.bytes) {
+            Some(i) => i + 1,
+            None => self.bytes.len(),
+        };
+        let (record, rest) = self.bytes.split_at(end);
+        self.bytes = rest;
+        Some(record)
+    }
+}
+
+/// Reports records that could not be read on stderr, naming the input and line of each, and
+/// counts them.
+pub struct ErrorLog {
+    source: String,
+    policy: ReadErrorPolicy,
+    count: usize,
+}
+
+impl ErrorLog {
+    pub fn new(config: &Config) -> Self {
+        let source = match &config.opt.input {
+            Some(path) => path.display().to_string(),
+            None => String::from("<stdin>"),
+        };
+        ErrorLog {
+            source,
+            policy: config.opt.on_read_error,
+            count: 0,
+        }
+    }
+
+    /// The name of the input, as errors refer to it.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Report that the record on `line` could not be read, returning whether to go on to the next.
+    pub fn report<E: Display>(&mut self, line: usize, e: E) -> bool {
+        let error = Error::Read {
+            source: self.source.clone(),
+            line,
+            reason: e.to_string(),
+        };
+        eprintln!("{}", error);
+        self.count += 1;
+        self.policy == ReadErrorPolicy::Skip
+    }
+
+    /// Fail if any record could not be read.
+    pub fn finish(self) -> Result<(), Error> {
+        match self.count {
+            0 => Ok(()),
+            n => Err(Error::Unread(n)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_keep_terminators() {
+        let records: Vec<_> = Records::new(b"a b\nc\r\nd", b'\n').collect();
+        assert_eq!(vec![&b"a b\n"[..], b"c\r\n", b"d"], records);
+    }
+
+    #[test]
+    fn records_keep_invalid_utf8() {
+        let mut records = Records::new(b"\xff\nok\n", b'\n');
+        assert_eq!(Some(&b"\xff\n"[..]), records.next());
+        assert_eq!(Some(&b"ok\n"[..]), records.next());
+    }
+
+    #[test]
+    fn for_each_skips_invalid_utf8() {
+        let config = Config::from_iter(vec!["choose", "0"]);
+        let mut log = ErrorLog::new(&config);
+        let mut seen = Vec::new();
+        for_each(&b"a\n\xff\nc\n"[..], &config, &mut log, |line, l| {
+            seen.push((line, String::from(l)));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            vec![(1, String::from("a\n")), (3, String::from("c\n"))],
+            seen
+        );
+        assert_eq!(5, log.finish().unwrap_err().exit_code());
+    }
+
+    #[test]
+    fn error_log_follows_policy() {
+        let config = Config::from_iter(vec!["choose", "0"]);
+        let mut log = ErrorLog::new(&config);
+        assert!(log.report(3, "bad"));
+        assert_eq!(5, log.finish().unwrap_err().exit_code());
+
+        let config = Config::from_iter(vec!["choose", "0", "--on-read-error", "abort"]);
+        let mut log = ErrorLog::new(&config);
+        assert!(!log.report(3, "bad"));
+    }
+
+    #[test]
+    fn for_each_skips_rejected_records() {
+        let config = Config::from_iter(vec!["choose", "0"]);
+        let mut log = ErrorLog::new(&config);
+        let mut seen = Vec::new();
+        for_each(&b"a\nb\nc\n"[..], &config, &mut log, |line, _| {
+            seen.push(line);
+            match line {
+                2 => Err(io::Error::new(io::ErrorKind::InvalidData, "bad")),
+                _ => Ok(()),
+            }
+        })
+        .unwrap();
+        assert_eq!(vec![1, 2, 3], seen);
+        assert_eq!(5, log.finish().unwrap_err().exit_code());
+    }
+
+    #[test]
+    fn for_each_returns_write_errors() {
+        let config = Config::from_iter(vec!["choose", "0"]);
+        let mut log = ErrorLog::new(&config);
+        let result = for_each(&b"a\nb\n"[..], &config, &mut log, |_, _| {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        });
+        assert_eq!(io::ErrorKind::BrokenPipe, result.unwrap_err().kind());
+        assert!(log.finish().is_ok());
+    }
 
-        self.reader
-            .read_line(buffer)
-            .map(|u| if u == 0 { None } else { Some(buffer) })
-            .transpose()
+    #[test]
+    fn map_regular_file() {
+        let file = File::open("test/lorem.txt").unwrap();
+        assert!(map(&file).is_some());
     }
 }
@@ -0,0 +1,333 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+use crate::error::Error;
+
+const KNOWN: &str = "expected upper, lower, trim, trim-chars:C, truncate:N, pad:N, \
+                     replace:/re/repl/, basename or dirname";
+
+/// A change made to each item of a choice before it is written.
+#[derive(Debug, Clone)]
+pub enum Transform {
+    Upper,
+    Lower,
+    Trim,
+    /// Trim any of these characters from both ends
+    TrimChars(String),
+    /// Keep at most this many characters
+    Truncate(usize),
+    /// Pad with spaces on the right to at least this many display columns
+    Pad(usize),
+    /// Replace every match of the regex, which may refer to capture groups as `$1`
+    Replace(Regex, String),
+    Basename,
+    Dirname,
+}
+
+/// The transforms applied, in order, to each item of a choice.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline(Vec<Transform>);
+
+impl Pipeline {
+    /// Parse `|`-separated transforms such as `trim|upper|truncate:8`.
+    pub fn parse(src: &str) -> Result<Self, Error> {
+        Pipeline::parse_from(src, 0)
+    }
+
+    /// Parse the transforms in `src[start..]`, pointing any error at the part of `src` at fault.
+    pub fn parse_from(src: &str, start: usize) -> Result<Self, Error> {
+        let mut transforms = Vec::new();
+        let mut start = start;
+        loop {
+            let (transform, end) = Transform::parse(src, start)?;
+            transforms.push(transform);
+            if end == src.len() {
+                return Ok(Pipeline(transforms));
+            }
+            start = end + 1;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The transforms of `self` followed by those of `other`.
+    pub fn then(&self, other: &Pipeline) -> Pipeline {
+        Pipeline(self.0.iter().chain(&other.0).cloned().collect())
+    }
+
+    pub fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        self.0.iter().fold(text, |text, t| t.apply(text))
+    }
+}
+
+impl Transform {
+    /// Parse the transform at `src[start..]`, returning it and where it ends, which is either at
+    /// the end of `src` or at the `|` before the next transform.
+    fn parse(src: &str, start: usize) -> Result<(Self, usize), Error> {
+        if src[start..].starts_with("replace:") {
+            return Transform::parse_replace(src, start);
+        }
+
+        let end = src[start..].find('|').map_or(src.len(), |i| start + i);
+        let item = &src[start..end];
+        let (name, arg) = match item.find(':') {
+            Some(i) => (&item[..i], Some(&item[i + 1..])),
+            None => (item, None),
+        };
+        let number = || {
+            arg.and_then(|a| a.parse().ok()).ok_or_else(|| {
+                Error::arg(
+                    src,
+                    start..end,
+                    format!("{} needs a number, e.g. {}:8", name, name),
+                )
+            })
+        };
+
+        let transform = match (name, arg) {
+            ("upper", None) => Transform::Upper,
+            ("lower", None) => Transform::Lower,
+            ("trim", None) => Transform::Trim,
+            ("trim-chars", Some(chars)) if !chars.is_empty() => {
+                Transform::TrimChars(String::from(chars))
+            }
+            ("truncate", _) => Transform::Truncate(number()?),
+            ("pad", _) => Transform::Pad(number()?),
+            ("basename", None) => Transform::Basename,
+            ("dirname", None) => Transform::Dirname,
+            _ => return Err(Error::arg(src, start..end, KNOWN)),
+        };
+        Ok((transform, end))
+    }
+
+    /// Parse `replace:/re/repl/`, where any character may stand in for `/`.
+    fn parse_replace(src: &str, start: usize) -> Result<(Self, usize), Error> {
+        let args = start + "replace:".len();
+        let usage = |end| Error::arg(src, start..end, "replace needs /pattern/replacement/");
+        let delimiter = src[args..].chars().next().ok_or_else(|| usage(src.len()))?;
+
+        let pattern = args + delimiter.len_utf8();
+        let pattern_end = src[pattern..]
+            .find(delimiter)
+            .map(|i| pattern + i)
+            .ok_or_else(|| usage(src.len()))?;
+        let replacement = pattern_end + delimiter.len_utf8();
+        let replacement_end = src[replacement..]
+            .find(delimiter)
+            .map(|i| replacement + i)
+            .ok_or_else(|| usage(src.len()))?;
+        let end = replacement_end + delimiter.len_utf8();
+        if end != src.len() && !src[end..].starts_with('|') {
+            return Err(usage(end));
+        }
+
+        let regex = Regex::new(&src[pattern..pattern_end])
+            .map_err(|e| Error::arg(src, pattern..pattern_end, e))?;
+        let replacement = String::from(&src[replacement..replacement_end]);
+        Ok((Transform::Replace(regex, replacement), end))
+    }
+
+    fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        match self {
+            Transform::Upper => Cow::Owned(text.to_uppercase()),
+            Transform::Lower => Cow::Owned(text.to_lowercase()),
+            Transform::Trim => slice(text, str::trim),
+            Transform::TrimChars(chars) => slice(text, |s| s.trim_matches(|c| chars.contains(c))),
+            Transform::Truncate(n) => match text.char_indices().nth(*n) {
+                Some((i, _)) => slice(text, |s| &s[..i]),
+                None => text,
+            },
+            Transform::Pad(n) => match n.checked_sub(text.width()) {
+                Some(pad) if pad > 0 => Cow::Owned(format!("{}{:2$}", text, "", pad)),
+                _ => text,
+            },
+            Transform::Replace(regex, replacement) => {
+                match regex.replace_all(&text, replacement.as_str()) {
+                    Cow::Owned(s) => Cow::Owned(s),
+                    Cow::Borrowed(_) => text,
+                }
+            }
+            Transform::Basename => slice(text, basename),
+            Transform::Dirname => slice(text, dirname),
+        }
+    }
+}
+
+/// Narrow `text` to the part of it that `f` returns, without copying borrowed text.
+fn slice<'a, F: Fn(&str) -> &str>(text: Cow<'a, str>, f: F) -> Cow<'a, str> {
+    match text {
+        Cow::Borrowed(s) => Cow::Borrowed(f(s)),
+        Cow::Owned(s) => Cow::Owned(String::from(f(&s))),
+    }
+}
+
+/// The last component of a `/`-separated path, like `basename(1)`.
+fn basename(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return &path[..path.len().min(1)];
+    }
+    match trimmed.rfind('/') {
+        Some(i) => &trimmed[i + 1..],
+        None => trimmed,
+    }
+}
+
+/// All but the last component of a `/`-separated path, like `dirname(1)`.
+fn dirname(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if path.is_empty() { "." } else { "/" };
+    }
+    match trimmed.rfind('/') {
+        Some(i) => match trimmed[..i].trim_end_matches('/') {
+            "" => "/",
+            parent => parent,
+        },
+        None => ".",
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transform::Upper => write!(f, "upper"),
+            Transform::Lower => write!(f, "lower"),
+            Transform::Trim => write!(f, "trim"),
+            Transform::TrimChars(chars) => write!(f, "trim-chars:{}", chars),
+            Transform::Truncate(n) => write!(f, "truncate:{}", n),
+            Transform::Pad(n) => write!(f, "pad:{}", n),
+            Transform::Replace(regex, replacement) => {
+                let delimiter = ['/', '#', '@', '!', '%', '~']
+                    .iter()
+                    .find(|d| !regex.as_str().contains(**d) && !replacement.contains(**d))
+                    .unwrap_or(&'/');
+                write!(
+                    f,
+                    "replace:{1}{0}{1}{2}{1}",
+                    regex.as_str(),
+                    delimiter,
+                    replacement
+                )
+            }
+            Transform::Basename => write!(f, "basename"),
+            Transform::Dirname => write!(f, "dirname"),
+        }
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, transform) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "|")?;
+            }
+            write!(f, "{}", transform)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(pipeline: &str, text: &str) -> String {
+        Pipeline::parse(pipeline)
+            .unwrap()
+            .apply(Cow::Borrowed(text))
+            .into_owned()
+    }
+
+    #[test]
+    fn apply_case() {
+        assert_eq!("ÉTÉ", apply("upper", "été"));
+        assert_eq!("abc", apply("lower", "AbC"));
+    }
+
+    #[test]
+    fn apply_trims() {
+        assert_eq!("a b", apply("trim", "  a b\t"));
+        assert_eq!("a,b", apply("trim-chars:\"',", "\"a,b',"));
+    }
+
+    #[test]
+    fn apply_truncate_by_character() {
+        assert_eq!("hél", apply("truncate:3", "héllo"));
+        assert_eq!("hi", apply("truncate:3", "hi"));
+    }
+
+    #[test]
+    fn apply_pad_by_column() {
+        assert_eq!("ab  ", apply("pad:4", "ab"));
+        assert_eq!("日本", apply("pad:4", "日本"));
+        assert_eq!("abcde", apply("pad:4", "abcde"));
+    }
+
+    #[test]
+    fn apply_replace() {
+        assert_eq!("01.2024", apply("replace:/(\\d+)-(\\d+)/$2.$1/", "2024-01"));
+        assert_eq!("x|y", apply("replace:#-#|#", "x-y"));
+    }
+
+    #[test]
+    fn apply_paths() {
+        assert_eq!("c.txt", apply("basename", "/a/b/c.txt"));
+        assert_eq!("b", apply("basename", "a/b/"));
+        assert_eq!("/", apply("basename", "/"));
+        assert_eq!("/a/b", apply("dirname", "/a/b/c.txt"));
+        assert_eq!("/", apply("dirname", "/a"));
+        assert_eq!(".", apply("dirname", "a"));
+    }
+
+    #[test]
+    fn apply_in_order() {
+        assert_eq!("ABC", apply("trim|truncate:3|upper", "  abcdef"));
+        assert_eq!("abc  ", apply("truncate:3|pad:5", "abcdef"));
+    }
+
+    #[test]
+    fn apply_leaves_borrowed_text_unchanged() {
+        let pipeline = Pipeline::parse("trim|truncate:3").unwrap();
+        assert!(matches!(
+            pipeline.apply(Cow::Borrowed(" ab ")),
+            Cow::Borrowed("ab")
+        ));
+    }
+
+    #[test]
+    fn parse_errors_point_at_transform() {
+        for (src, span) in &[
+            ("0|upper|nope", 8..12),
+            ("0|truncate:x", 2..12),
+            ("0|trim-chars:", 2..13),
+            ("0|replace:/a/", 2..13),
+            ("0|replace:/a/b/x", 2..15),
+            ("0|replace:/(/b/", 11..12),
+        ] {
+            match Pipeline::parse_from(src, 2) {
+                Err(Error::Arg { span: s, .. }) => assert_eq!(*span, s, "{}", src),
+                _ => panic!("expected an argument error for {}", src),
+            }
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for src in &[
+            "upper|trim-chars:ab|truncate:8|pad:3",
+            "replace:/a/b/|basename",
+        ] {
+            assert_eq!(*src, Pipeline::parse(src).unwrap().to_string());
+        }
+        assert_eq!(
+            "replace:#a/b#c#",
+            Pipeline::parse("replace:|a/b|c|").unwrap().to_string()
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use crate::config::{Config, Label, TransposeMode};
+use crate::debug;
+use crate::reader::{self, ErrorLog};
+
+/// An item chosen from a record.
+struct Item {
+    /// Index of the choice that chose it.
+    choice: usize,
+    /// Index of the item in the record.
+    index: usize,
+    /// Position of the item in the selection of its choice, counted from the first item printed.
+    position: usize,
+    text: String,
+}
+
+/// Print what the choices select from each record of `read` turned into rows, either record by
+/// record or, with `--transpose-mode table`, once the whole input has been read.
+pub fn run<R: Read, W: Write>(
+    read: R,
+    config: &Config,
+    handle: &mut W,
+    log: &mut ErrorLog,
+) -> io::Result<()> {
+    let mut header: Option<Vec<Item>> = None;
+    let mut table: Vec<Vec<Item>> = Vec::new();
+    let mut printed = false;
+    reader::for_each(read, config, log, |line, l| {
+        debug::print_record(line, l.as_bytes(), config);
+        let items = items(config.split_terminator(l).0, config);
+        if config.opt.label == Some(Label::Header) && header.is_none() {
+            header = Some(items);
+            return Ok(());
+        }
+
+        match config.opt.transpose_mode {
+            TransposeMode::Record => {
+                if printed {
+                    handle.write_all(&[config.opt.terminator])?;
+                }
+                printed = true;
+                print_record(&items, header.as_deref(), config, handle)
+            }
+            TransposeMode::Table => {
+                table.push(items);
+                Ok(())
+            }
+        }
+    })?;
+
+    if config.opt.transpose_mode == TransposeMode::Table {
+        print_table(&table, header.as_deref(), config, handle)?;
+    }
+    Ok(())
+}
+
+/// The items that each choice selects from `line`, in the order they are printed.
+fn items(line: &str, config: &Config) -> Vec<Item> {
+    let mut items = Vec::new();
+    for (i, choice) in config.opt.choice.iter().enumerate() {
+        for (index, position, text) in choice.indexed_items(line, config) {
+            items.push(Item {
+                choice: i,
+                index,
+                position,
+                text: text.into_owned(),
+            });
+        }
+    }
+    items
+}
+
+/// Write each of the items of one record on a row of its own.
+fn print_record<W: Write>(
+    items: &[Item],
+    header: Option<&[Item]>,
+    config: &Config,
+    handle: &mut W,
+) -> io::Result<()> {
+    for item in items {
+        print_label(
+            item.choice,
+            item.position,
+            item.index,
+            header,
+            config,
+            handle,
+        )?;
+        handle.write_all(item.text.as_bytes())?;
+        handle.write_all(&[config.opt.terminator])?;
+    }
+    Ok(())
+}
+
+/// Write the items that each choice chose from the same position in its selection of every record on
+/// a row of their own, separated by the output separator of that choice. A record without such an
+/// item, as when it is shorter or the field is empty, leaves its place in the row empty.
+fn print_table<W: Write>(
+    table: &[Vec<Item>],
+    header: Option<&[Item]>,
+    config: &Config,
+    handle: &mut W,
+) -> io::Result<()> {
+    let mut rows: BTreeMap<(usize, usize), (usize, Vec<&str>)> = BTreeMap::new();
+    for (record, items) in table.iter().enumerate() {
+        for item in items {
+            let (_, cells) = rows
+                .entry((item.choice, item.position))
+                .or_insert_with(|| (item.index, vec![""; table.len()]));
+            cells[record] = &item.text;
+        }
+    }
+
+    for ((choice, position), (index, cells)) in rows {
+        print_label(choice, position, index, header, config, handle)?;
+        let separator = config.opt.choice[choice].inner_separator(config);
+        for (i, text) in cells.iter().enumerate() {
+            if i > 0 {
+                handle.write_all(separator)?;
+            }
+            handle.write_all(text.as_bytes())?;
+        }
+        handle.write_all(&[config.opt.terminator])?;
+    }
+    Ok(())
+}
+
+/// Write the label of the row of the items that `choice` chose from `position` in its selection,
+/// which is `index` in the record, followed by the outer separator, if `--label` asks for one.
+/// A header label is the item at the same position in the selection from the header.
+fn print_label<W: Write>(
+    choice: usize,
+    position: usize,
+    index: usize,
+    header: Option<&[Item]>,
+    config: &Config,
+    handle: &mut W,
+) -> io::Result<()> {
+    match config.opt.label {
+        None => return Ok(()),
+        Some(Label::Index) => write!(handle, "{}", index)?,
+        Some(Label::Header) => {
+            let label = header
+                .unwrap_or_default()
+                .iter()
+                .find(|item| (item.choice, item.position) == (choice, position));
+            if let Some(item) = label {
+                handle.write_all(item.text.as_bytes())?;
+            }
+        }
+    }
+    handle.write_all(&config.outer_separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transpose(args: &[&str], input: &str) -> String {
+        let mut argv = vec!["choose", "--transpose"];
+        argv.extend_from_slice(args);
+        let config = Config::from_iter(argv);
+        let mut log = ErrorLog::new(&config);
+        let mut out = Vec::new();
+        run(input.as_bytes(), &config, &mut out, &mut log).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn transpose_records() {
+        assert_eq!("a\nc\n\nd\nf\n", transpose(&["0", "2"], "a b c\nd e f\n"));
+    }
+
+    #[test]
+    fn transpose_in_choice_order() {
+        assert_eq!("c\nb\na\n", transpose(&["2:0"], "a b c\n"));
+        assert_eq!("c\na\n", transpose(&["2", "0"], "a b c\n"));
+    }
+
+    #[test]
+    fn transpose_with_index_labels() {
+        assert_eq!(
+            "1:b\n3:d\n",
+            transpose(&["1", "-1", "--label", "index", "-o", ":"], "a b c d\n")
+        );
+    }
+
+    #[test]
+    fn transpose_with_header_labels() {
+        assert_eq!(
+            "name ann\nage 31\n\nname bob\nage 42\n",
+            transpose(&["0:1", "--label", "header"], "name age\nann 31\nbob 42\n")
+        );
+    }
+
+    #[test]
+    fn transpose_table() {
+        assert_eq!(
+            "a,d,g\nb,e,\n",
+            transpose(
+                &["0:1", "--transpose-mode", "table", "-o", ","],
+                "a b c\nd e f\ng\n"
+            )
+        );
+    }
+
+    #[test]
+    fn transpose_table_keeps_empty_fields_in_place() {
+        assert_eq!(
+            "0 a d\n1  e\n2 c f\n",
+            transpose(
+                &[
+                    "0:2",
+                    "-n",
+                    "-f",
+                    ",",
+                    "--transpose-mode",
+                    "table",
+                    "--label",
+                    "index"
+                ],
+                "a,,c\nd,e,f\n"
+            )
+        );
+    }
+
+    #[test]
+    fn transpose_table_reversed() {
+        assert_eq!(
+            "c,d\nb,\na,\n",
+            transpose(
+                &["2:0", "--transpose-mode", "table", "-o", ","],
+                "a b c\nd\n"
+            )
+        );
+    }
+
+    #[test]
+    fn transpose_table_ragged_negative_index() {
+        assert_eq!(
+            "c,e\n",
+            transpose(
+                &["-1", "--transpose-mode", "table", "-o", ","],
+                "a b c\nd e\n"
+            )
+        );
+        assert_eq!(
+            "z c e\n",
+            transpose(
+                &["-1", "--transpose-mode", "table", "--label", "header"],
+                "x y z\na b c\nd e\n"
+            )
+        );
+    }
+
+    #[test]
+    fn transpose_header_labels_follow_position() {
+        assert_eq!(
+            "z 3\n\nz 5\n",
+            transpose(&["-1", "--label", "header"], "x y z\n1 2 3\n4 5\n")
+        );
+    }
+
+    #[test]
+    fn transpose_header_labels_follow_index() {
+        assert_eq!(
+            "x 1\nz 3\n",
+            transpose(
+                &["0:2", "-n", "-f", ",", "--label", "header"],
+                "x,y,z\n1,,3\n"
+            )
+        );
+    }
+
+    #[test]
+    fn transpose_table_with_labels() {
+        assert_eq!(
+            "0 a d\n2 c-f\n",
+            transpose(
+                &["0", "2/-", "--transpose-mode", "table", "--label", "index"],
+                "a b c\nd e f\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::Hasher;
+use std::io::{self, BufWriter, Write};
+
+use crate::config::{Config, UniqueMode};
+use crate::debug;
+use crate::plan::Plan;
+
+/// Number of bits in the filter of `--unique-mode approx`: 16 MiB.
+const BLOOM_BITS: u64 = 1 << 27;
+
+/// Number of bits set for each record in the filter of `--unique-mode approx`.
+const BLOOM_HASHES: u64 = 4;
+
+/// Prints output records through a `Plan`, leaving out those that repeat an earlier one when
+/// `--unique` is given.
+pub struct Unique {
+    seen: Option<Seen>,
+    /// The output of the current record, compared before it is written
+    record: BufWriter<Vec<u8>>,
+    suppressed: usize,
+}
+
+/// The output records that a new one is compared with.
+enum Seen {
+    /// Only the previous record, if any
+    Adjacent(Option<Vec<u8>>),
+    Global(HashSet<Vec<u8>>),
+    Approx(Bloom),
+}
+
+/// A Bloom filter, which remembers records in fixed memory at the cost of rarely mistaking a new
+/// record for one already seen.
+struct Bloom {
+    bits: Vec<u64>,
+}
+
+impl Unique {
+    pub fn new(config: &Config) -> Self {
+        let seen = match config.opt.unique_mode {
+            _ if !config.opt.unique => None,
+            UniqueMode::Adjacent => Some(Seen::Adjacent(None)),
+            UniqueMode::Global => Some(Seen::Global(HashSet::new())),
+            UniqueMode::Approx => Some(Seen::Approx(Bloom::new())),
+        };
+        Unique {
+            seen,
+            // without a buffer of its own, everything written goes straight to the `Vec`
+            record: BufWriter::with_capacity(0, Vec::new()),
+            suppressed: 0,
+        }
+    }
+
+    /// Print the output record for `line` with `plan`, unless it repeats one already printed.
+    pub fn print_record<W: Write>(
+        &mut self,
+        plan: &mut Plan,
+        line: &[u8],
+        config: &Config,
+        handle: &mut BufWriter<W>,
+    ) -> io::Result<()> {
+        let seen = match &mut self.seen {
+            Some(seen) => seen,
+            None => return plan.print_record(line, config, handle),
+        };
+
+        self.record.get_mut().clear();
+        plan.print_choices(line, config, &mut self.record)?;
+        if seen.insert(self.record.get_ref()) {
+            handle.write_all(self.record.get_ref())?;
+            Plan::print_terminator(line, config, handle)
+        } else {
+            self.suppressed += 1;
+            Ok(())
+        }
+    }
+
+    /// With `-d`, report how many records were left out.
+    pub fn finish(&self, config: &Config) {
+        if self.seen.is_some() {
+            debug::print_suppressed(self.suppressed, config);
+        }
+    }
+}
+
+impl Seen {
+    /// Remember `record`, returning whether it is new.
+    fn insert(&mut self, record: &[u8]) -> bool {
+        match self {
+            Seen::Adjacent(Some(previous)) if previous[..] == *record => false,
+            Seen::Adjacent(previous) => {
+                let previous = previous.get_or_insert_with(Vec::new);
+                previous.clear();
+                previous.extend_from_slice(record);
+                true
+            }
+            Seen::Global(seen) => !seen.contains(record) && seen.insert(record.to_vec()),
+            Seen::Approx(bloom) => bloom.insert(record),
+        }
+    }
+}
+
+impl Bloom {
+    fn new() -> Self {
+        Bloom {
+            bits: vec![0; (BLOOM_BITS / 64) as usize],
+        }
+    }
+
+    /// Set the bits of `record`, returning whether any of them was not set already.
+    fn insert(&mut self, record: &[u8]) -> bool {
+        let mut first = DefaultHasher::new();
+        first.write(record);
+        let mut second = DefaultHasher::new();
+        second.write_u8(0xff);
+        second.write(record);
+        let (h1, h2) = (first.finish(), second.finish() | 1);
+
+        let mut new = false;
+        for i in 0..BLOOM_HASHES {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % BLOOM_BITS;
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+            new |= self.bits[word] & mask == 0;
+            self.bits[word] |= mask;
+        }
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique(args: &[&str], lines: &[&str]) -> (String, usize) {
+        let mut argv = vec!["choose", "--unique"];
+        argv.extend_from_slice(args);
+        let config = Config::from_iter(argv);
+        let mut plan = Plan::new(&config.opt.choice);
+        let mut unique = Unique::new(&config);
+        let mut handle = BufWriter::new(Vec::new());
+        for line in lines {
+            // a record that cannot be read is left for the caller to report
+            let _ = unique.print_record(&mut plan, line.as_bytes(), &config, &mut handle);
+        }
+        let out = String::from_utf8(handle.into_inner().unwrap()).unwrap();
+        (out, unique.suppressed)
+    }
+
+    const LINES: &[&str] = &["a 1\n", "a 2\n", "b 3\n", "a 4\n"];
+
+    #[test]
+    fn unique_global_after_selection() {
+        assert_eq!((String::from("a\nb\n"), 2), unique(&["0"], LINES));
+    }
+
+    #[test]
+    fn unique_adjacent() {
+        assert_eq!(
+            (String::from("a\nb\na\n"), 1),
+            unique(&["0", "--unique-mode", "adjacent"], LINES)
+        );
+    }
+
+    #[test]
+    fn unique_approx() {
+        assert_eq!(
+            (String::from("a\nb\n"), 2),
+            unique(&["0", "--unique-mode", "approx"], LINES)
+        );
+    }
+
+    #[test]
+    fn unique_compares_without_terminator() {
+        assert_eq!(
+            (String::from("a\r\n"), 1),
+            unique(&["0", "--preserve-terminators"], &["a\r\n", "a"])
+        );
+    }
+
+    #[test]
+    fn unique_skips_bad_json_records() {
+        assert_eq!(
+            (String::from("1\n"), 1),
+            unique(
+                &["--json", ".a"],
+                &["{\"a\":1}\n", "not json\n", "{\"a\":1}\n"]
+            )
+        );
+    }
+
+    #[test]
+    fn unique_keeps_first_empty_record() {
+        assert_eq!(
+            (String::from("\na\n"), 1),
+            unique(
+                &["1", "--unique-mode", "adjacent"],
+                &["x\n", "y\n", "x a\n"]
+            )
+        );
+    }
+}
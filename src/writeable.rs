@@ -1,20 +1,105 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// Something that can be written as a single item of a choice, straight from borrowed data.
 pub trait Writeable {
-    fn to_byte_buf(&self) -> Box<[u8]>;
+    fn is_empty(&self) -> bool;
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    /// The item as text, for transforms to change. Bytes that are not valid UTF-8 are replaced.
+    fn to_text(&self) -> Cow<'_, str>;
 }
 
 impl Writeable for &str {
-    fn to_byte_buf(&self) -> Box<[u8]> {
-        return Box::from(self.as_bytes());
+    fn is_empty(&self) -> bool {
+        str::is_empty(self)
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.as_bytes())
+    }
+
+    fn to_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl Writeable for &[u8] {
+    fn is_empty(&self) -> bool {
+        <[u8]>::is_empty(self)
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self)
+    }
+
+    fn to_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self)
     }
 }
 
 impl Writeable for char {
-    fn to_byte_buf(&self) -> Box<[u8]> {
+    fn is_empty(&self) -> bool {
+        false
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         let mut buf = [0; 4];
-        return self
-            .encode_utf8(&mut buf)
-            .to_owned()
-            .into_boxed_str()
-            .into_boxed_bytes();
+        writer.write_all(self.encode_utf8(&mut buf).as_bytes())
+    }
+
+    fn to_text(&self) -> Cow<'_, str> {
+        Cow::Owned(self.to_string())
+    }
+}
+
+impl Writeable for &serde_json::Value {
+    fn is_empty(&self) -> bool {
+        match self {
+            serde_json::Value::String(s) => s.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            serde_json::Value::String(s) => writer.write_all(s.as_bytes()),
+            v => serde_json::to_writer(writer, v).map_err(io::Error::from),
+        }
+    }
+
+    fn to_text(&self) -> Cow<'_, str> {
+        match self {
+            serde_json::Value::String(s) => Cow::Borrowed(s),
+            v => Cow::Owned(v.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written<Wa: Writeable>(w: Wa) -> Vec<u8> {
+        let mut buf = Vec::new();
+        w.write_to(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn write_multibyte_char() {
+        assert_eq!("\u{e9}".as_bytes(), &written('\u{e9}')[..]);
+    }
+
+    #[test]
+    fn write_json_string_raw() {
+        assert_eq!(b"ab", &written(&serde_json::json!("ab"))[..]);
+    }
+
+    #[test]
+    fn write_json_object_compact() {
+        assert_eq!(
+            b"{\"a\":[1,2]}",
+            &written(&serde_json::json!({"a": [1, 2]}))[..]
+        );
     }
 }
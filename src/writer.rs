@@ -1,31 +1,57 @@
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 
+use crate::choice::Choice;
 use crate::config::Config;
 use crate::writeable::Writeable;
 
+/// SGR sequences cycled through to color the output of each choice with `--color`.
+pub const PALETTE: &[&str] = &[
+    "\x1b[31m", "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m",
+];
+
+pub const RESET: &[u8] = b"\x1b[0m";
+
 pub trait WriteReceiver {
-    fn write_choice<Wa: Writeable>(&mut self, b: Wa, config: &Config, print_separator: bool);
-    fn write_separator(&mut self, config: &Config);
+    fn write_choice<Wa: Writeable>(
+        &mut self,
+        b: Wa,
+        choice: &Choice,
+        config: &Config,
+        print_separator: bool,
+    ) -> io::Result<()>;
+    fn write_separator(&mut self, separator: &[u8]) -> io::Result<()>;
 }
 
 impl<W: Write> WriteReceiver for BufWriter<W> {
-    fn write_choice<Wa: Writeable>(&mut self, b: Wa, config: &Config, print_separator: bool) {
-        let num_bytes_written = match self.write(&b.to_byte_buf()) {
-            Ok(x) => x,
-            Err(e) => {
-                eprintln!("Failed to write to output: {}", e);
-                0
-            }
-        };
-        if num_bytes_written > 0 && print_separator {
-            self.write_separator(config);
-        };
+    fn write_choice<Wa: Writeable>(
+        &mut self,
+        b: Wa,
+        choice: &Choice,
+        config: &Config,
+        print_separator: bool,
+    ) -> io::Result<()> {
+        if b.is_empty() {
+            return Ok(());
+        }
+        if let Some(color) = choice.color {
+            self.write_all(color.as_bytes())?;
+        }
+        if choice.transforms.is_empty() {
+            b.write_to(self)?;
+        } else {
+            let text = choice.transforms.apply(b.to_text());
+            text.as_ref().write_to(self)?;
+        }
+        if choice.color.is_some() {
+            self.write_all(RESET)?;
+        }
+        if print_separator {
+            self.write_separator(choice.inner_separator(config))?;
+        }
+        Ok(())
     }
 
-    fn write_separator(&mut self, config: &Config) {
-        match self.write(&config.output_separator) {
-            Ok(_) => (),
-            Err(e) => eprintln!("Failed to write to output: {}", e),
-        }
+    fn write_separator(&mut self, separator: &[u8]) -> io::Result<()> {
+        self.write_all(separator)
     }
 }
@@ -18,6 +18,9 @@ diff -w <(cargo run -- 1:3 -o % -i ${test_dir}/lorem.txt 2>/dev/null) <(cat "${t
 diff -w <(cargo run -- 1 3 -o % -i ${test_dir}/lorem.txt 2>/dev/null) <(cat "${test_dir}/choose_1_3of%.txt")
 diff -w <(cargo run -- 1 3 -o '' -i ${test_dir}/lorem.txt 2>/dev/null) <(cat "${test_dir}/choose_1_3of.txt")
 diff -w <(cargo run -- 3:6 -c -i ${test_dir}/lorem.txt 2>/dev/null) <(cat "${test_dir}/choose_0_3_c.txt")
+diff -w <(cargo run -- 0:1 --threads 2 -i ${test_dir}/lorem.txt 2>/dev/null) <(cat "${test_dir}/choose_0:1.txt")
+diff -w <(cargo run -- 3:6 -b -i ${test_dir}/lorem.txt 2>/dev/null) <(cat "${test_dir}/choose_0_3_c.txt")
+diff -w <(cargo run -- --json user.name tags.-1 items.0:1 -i ${test_dir}/records.jsonl 2>/dev/null) <(cat "${test_dir}/choose_json_user.name_tags.-1_items.0:1.txt")
 # add tests for different delimiters
 # add tests using piping
 
@@ -49,6 +52,30 @@ if [ $r -ne 3 ]; then
 fi
 rm -f $file
 
+yes "a b c" | head -n 100000 > /tmp/choose_long
+cargo run -q -- 0 -i /tmp/choose_long 2>/tmp/choose_stderr | head -n 1 >/dev/null
+r=${PIPESTATUS[0]}
+if [ $r -ne 141 ] || [ -s /tmp/choose_stderr ]; then
+  echo "Failed to exit quietly with 141 on broken pipe"
+  exit 1
+fi
+
+cargo run -- 0 -i /tmp/choose_long >/dev/full 2>/dev/null
+r=$?
+if [ $r -ne 4 ]; then
+  echo "Failed to return 4 on write error"
+  exit 1
+fi
+rm -f /tmp/choose_long /tmp/choose_stderr
+
+printf 'a b\n\xff x\nc d\n' | cargo run -q -- 1 2>/tmp/choose_stderr >/dev/null
+r=${PIPESTATUS[1]}
+if [ $r -ne 5 ] || ! grep -q '^<stdin>:2: ' /tmp/choose_stderr; then
+  echo "Failed to return 5 and report the line of an unreadable record"
+  exit 1
+fi
+rm -f /tmp/choose_stderr
+
 cd $orig_dir
 
 printf "\033[1;32mAll tests passed\033[0m\n"